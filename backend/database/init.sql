@@ -1,31 +1,231 @@
 CREATE TABLE IF NOT EXISTS users (
   id          SERIAL PRIMARY KEY,
   username    VARCHAR(64) NOT NULL UNIQUE,
-  password    VARCHAR(64) NOT NULL,
-  deleted_at  TIMESTAMPTZ DEFAULT NULL,
-  token       TEXT DEFAULT NULL
+  password    VARCHAR(255) NOT NULL,
+  role        VARCHAR(16) NOT NULL DEFAULT 'user',
+  verified_at TIMESTAMPTZ DEFAULT NULL,
+  totp_secret VARCHAR(64) DEFAULT NULL,
+  totp_enabled_at TIMESTAMPTZ DEFAULT NULL,
+  totp_last_step BIGINT DEFAULT NULL,
+  deleted_at  TIMESTAMPTZ DEFAULT NULL
 );
 
+-- accounts created before email verification existed count as verified,
+-- the default only fills the rows present when the column is added
+ALTER TABLE users ADD COLUMN IF NOT EXISTS verified_at TIMESTAMPTZ DEFAULT NOW();
+ALTER TABLE users ALTER COLUMN verified_at SET DEFAULT NULL;
+
+-- brings a users table created by an earlier version of this file up to date
+ALTER TABLE users ALTER COLUMN password TYPE VARCHAR(255);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role VARCHAR(16) NOT NULL DEFAULT 'user';
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret VARCHAR(64) DEFAULT NULL;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_enabled_at TIMESTAMPTZ DEFAULT NULL;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT DEFAULT NULL;
+ALTER TABLE users DROP COLUMN IF EXISTS token;
+
+CREATE TABLE IF NOT EXISTS projects (
+  id          SERIAL PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  name        VARCHAR(64) NOT NULL,
+  color       VARCHAR(7) DEFAULT NULL,
+  archived    BOOLEAN NOT NULL DEFAULT FALSE,
+  is_inbox    BOOLEAN NOT NULL DEFAULT FALSE,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_projects_user_id ON projects(user_id);
+-- every user has exactly one inbox
+CREATE UNIQUE INDEX IF NOT EXISTS idx_projects_inbox ON projects(user_id) WHERE is_inbox;
+
+-- the user a project belongs to is always its owner, other people get in
+-- through a membership
+CREATE TABLE IF NOT EXISTS project_members (
+  id          SERIAL PRIMARY KEY,
+  project_id  INTEGER NOT NULL,
+  user_id     INTEGER NOT NULL,
+  role        VARCHAR(16) NOT NULL CHECK (role IN ('viewer', 'editor', 'owner')),
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  UNIQUE (project_id, user_id),
+  CONSTRAINT fk_projects FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_members_user_id ON project_members(user_id);
+
+CREATE TABLE IF NOT EXISTS project_invitations (
+  id           SERIAL PRIMARY KEY,
+  project_id   INTEGER NOT NULL,
+  email        VARCHAR(64) NOT NULL,
+  role         VARCHAR(16) NOT NULL CHECK (role IN ('viewer', 'editor', 'owner')),
+  invited_by   INTEGER DEFAULT NULL,
+  expires_at   TIMESTAMPTZ NOT NULL,
+  accepted_at  TIMESTAMPTZ DEFAULT NULL,
+  declined_at  TIMESTAMPTZ DEFAULT NULL,
+  created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  CONSTRAINT fk_projects FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+  CONSTRAINT fk_users FOREIGN KEY (invited_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_invitations_email ON project_invitations(LOWER(email));
+
 CREATE TABLE IF NOT EXISTS tasks (
   id            SERIAL PRIMARY KEY,
   priority      VARCHAR(4) DEFAULT NULL,
   title         VARCHAR(255) NOT NULL,
   completed_at  TIMESTAMPTZ DEFAULT NULL,
+  status        VARCHAR(16) NOT NULL DEFAULT 'todo'
+                CHECK (status IN ('todo', 'in_progress', 'done', 'archived')),
   description   TEXT DEFAULT NULL,
   deleted_at    TIMESTAMPTZ DEFAULT NULL,
   user_id       INTEGER DEFAULT NULL, 
   is_default    BOOLEAN DEFAULT FALSE,
-  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id)
+  project_id    INTEGER DEFAULT NULL,
+  assignee_id   INTEGER DEFAULT NULL,
+  due_at        TIMESTAMPTZ DEFAULT NULL,
+  remind_at     TIMESTAMPTZ DEFAULT NULL,
+  reminded_at   TIMESTAMPTZ DEFAULT NULL,
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id),
+  CONSTRAINT fk_projects FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE SET NULL,
+  CONSTRAINT fk_assignees FOREIGN KEY (assignee_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+-- brings a tasks table created by an earlier version of this file up to date
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS status VARCHAR(16) NOT NULL DEFAULT 'todo'
+  CHECK (status IN ('todo', 'in_progress', 'done', 'archived'));
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS project_id INTEGER DEFAULT NULL
+  CONSTRAINT fk_projects REFERENCES projects(id) ON DELETE SET NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS assignee_id INTEGER DEFAULT NULL
+  CONSTRAINT fk_assignees REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS due_at TIMESTAMPTZ DEFAULT NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS remind_at TIMESTAMPTZ DEFAULT NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS reminded_at TIMESTAMPTZ DEFAULT NULL;
+-- completed_at used to be the only way to complete a task
+UPDATE tasks SET status = 'done'
+  WHERE completed_at IS NOT NULL AND status IN ('todo', 'in_progress');
+
+CREATE INDEX IF NOT EXISTS idx_tasks_project_id ON tasks(project_id);
+CREATE INDEX IF NOT EXISTS idx_tasks_assignee_id ON tasks(assignee_id);
+CREATE INDEX IF NOT EXISTS idx_tasks_due_at ON tasks(due_at);
+CREATE INDEX IF NOT EXISTS idx_tasks_pending_reminders ON tasks(remind_at)
+  WHERE reminded_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS task_items (
+  id            SERIAL PRIMARY KEY,
+  task_id       INTEGER NOT NULL,
+  title         VARCHAR(255) NOT NULL,
+  position      INTEGER NOT NULL DEFAULT 0,
+  completed_at  TIMESTAMPTZ DEFAULT NULL,
+  deleted_at    TIMESTAMPTZ DEFAULT NULL,
+  created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  CONSTRAINT fk_tasks FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
 );
 
-INSERT INTO users (username, password) VALUES ('deleteduser', '$2b$12$x3hs5oMgjHdcV1GUEElfsO19JtS6.ixJAX9Cj62GyhpdPAIW25sky');
+CREATE INDEX IF NOT EXISTS idx_task_items_task_id ON task_items(task_id);
 
-INSERT INTO tasks (title, deleted_at, user_id) VALUES (
-  'my deleted task',
-  NOW(),
-  (select id from users where username = 'deleteduser')
+CREATE TABLE IF NOT EXISTS sessions (
+  id            SERIAL PRIMARY KEY,
+  user_id       INTEGER NOT NULL,
+  jti           VARCHAR(64) NOT NULL UNIQUE,
+  user_agent    TEXT DEFAULT NULL,
+  ip            VARCHAR(45) DEFAULT NULL,
+  created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  last_seen_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at    TIMESTAMPTZ NOT NULL,
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
 );
 
-INSERT INTO tasks (priority, title, description, is_default) VALUES 
-  ('A', 'I am a task, you can complete me by checking the box', 'This is my description', true),
-  ('B', 'See my details for by clicking me', 'My description can be changed', true);
\ No newline at end of file
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+  id          SERIAL PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  session_id  INTEGER DEFAULT NULL,
+  family_id   VARCHAR(64) NOT NULL,
+  token_hash  VARCHAR(64) NOT NULL UNIQUE,
+  expires_at  TIMESTAMPTZ NOT NULL,
+  revoked_at  TIMESTAMPTZ DEFAULT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT fk_sessions FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family_id ON refresh_tokens(family_id);
+
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+  jti         VARCHAR(64) PRIMARY KEY,
+  expires_at  TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS login_attempts (
+  key             VARCHAR(128) PRIMARY KEY,
+  failures        INTEGER NOT NULL DEFAULT 0,
+  locked_until    TIMESTAMPTZ DEFAULT NULL,
+  last_failed_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS recovery_codes (
+  id          SERIAL PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  code_hash   VARCHAR(64) NOT NULL,
+  used_at     TIMESTAMPTZ DEFAULT NULL,
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_recovery_codes_user_id ON recovery_codes(user_id);
+
+CREATE TABLE IF NOT EXISTS access_tokens (
+  id            SERIAL PRIMARY KEY,
+  user_id       INTEGER NOT NULL,
+  name          VARCHAR(64) NOT NULL,
+  token_hash    VARCHAR(64) NOT NULL UNIQUE,
+  scopes        TEXT NOT NULL,
+  expires_at    TIMESTAMPTZ NOT NULL,
+  last_used_at  TIMESTAMPTZ DEFAULT NULL,
+  created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_access_tokens_user_id ON access_tokens(user_id);
+
+CREATE TABLE IF NOT EXISTS identities (
+  id          SERIAL PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  provider    VARCHAR(64) NOT NULL,
+  subject     VARCHAR(255) NOT NULL,
+  email       VARCHAR(255) DEFAULT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT uq_identities_provider_subject UNIQUE (provider, subject)
+);
+
+CREATE TABLE IF NOT EXISTS one_time_tokens (
+  id          SERIAL PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  purpose     VARCHAR(32) NOT NULL,
+  token_hash  VARCHAR(64) NOT NULL UNIQUE,
+  expires_at  TIMESTAMPTZ NOT NULL,
+  used_at     TIMESTAMPTZ DEFAULT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  CONSTRAINT fk_users FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- seed data, only added to a fresh database so the file can be run again
+-- to upgrade an existing one
+DO $$
+BEGIN
+IF NOT EXISTS (SELECT 1 FROM users WHERE username = 'deleteduser') THEN
+  INSERT INTO users (username, password, verified_at, deleted_at) VALUES ('deleteduser', '$2b$12$x3hs5oMgjHdcV1GUEElfsO19JtS6.ixJAX9Cj62GyhpdPAIW25sky', NOW(), NOW());
+
+  INSERT INTO tasks (title, deleted_at, user_id) VALUES (
+    'my deleted task',
+    NOW(),
+    (select id from users where username = 'deleteduser')
+  );
+
+  INSERT INTO tasks (priority, title, description, is_default) VALUES 
+    ('A', 'I am a task, you can complete me by checking the box', 'This is my description', true),
+    ('B', 'See my details for by clicking me', 'My description can be changed', true);
+END IF;
+END
+$$;
@@ -1,10 +1,29 @@
 use axum::extract::FromRef;
 use sea_orm::DatabaseConnection;
 
-use crate::utils::token_wrapper::TokenWrapper;
+use crate::utils::{
+    auth_cookies::AuthCookieConfig, jwt::JwtIssuer,
+    login_throttle::SharedLoginThrottle, mailer::SharedMailer,
+    notifier::SharedNotifier, oidc::OidcProviders,
+    password_hasher::PasswordHasher, retention::RetentionConfig,
+};
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
     pub db: DatabaseConnection,
-    pub jwt_secret: TokenWrapper,
+    pub jwt: JwtIssuer,
+    pub mailer: SharedMailer,
+    pub notifier: SharedNotifier,
+    pub login_throttle: SharedLoginThrottle,
+    pub password_hasher: PasswordHasher,
+    pub oidc: OidcProviders,
+    pub config: AppConfig,
+}
+
+#[derive(Clone)]
+pub struct AppConfig {
+    /// Base URL of the frontend, used to build the links sent by email.
+    pub app_url: String,
+    pub auth_cookie: AuthCookieConfig,
+    pub retention: RetentionConfig,
 }
@@ -2,5 +2,17 @@
 
 // pub mod prelude;
 
+pub mod access_tokens;
+pub mod identities;
+pub mod login_attempts;
+pub mod one_time_tokens;
+pub mod project_invitations;
+pub mod project_members;
+pub mod projects;
+pub mod recovery_codes;
+pub mod refresh_tokens;
+pub mod revoked_tokens;
+pub mod sessions;
+pub mod task_items;
 pub mod tasks;
 pub mod users;
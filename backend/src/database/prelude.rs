@@ -1,3 +1,15 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.
+pub use super::access_tokens::Entity as AccessTokens;
+pub use super::identities::Entity as Identities;
+pub use super::login_attempts::Entity as LoginAttempts;
+pub use super::one_time_tokens::Entity as OneTimeTokens;
+pub use super::project_invitations::Entity as ProjectInvitations;
+pub use super::project_members::Entity as ProjectMembers;
+pub use super::projects::Entity as Projects;
+pub use super::recovery_codes::Entity as RecoveryCodes;
+pub use super::refresh_tokens::Entity as RefreshTokens;
+pub use super::revoked_tokens::Entity as RevokedTokens;
+pub use super::sessions::Entity as Sessions;
+pub use super::task_items::Entity as TaskItems;
 pub use super::tasks::Entity as Tasks;
-pub use super::users::Entity as Users;
\ No newline at end of file
+pub use super::users::Entity as Users;
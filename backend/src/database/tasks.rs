@@ -10,15 +10,39 @@ pub struct Model {
     pub priority: Option<String>,
     pub title: String,
     pub completed_at: Option<DateTimeWithTimeZone>,
+    pub status: String,
     #[sea_orm(column_type = "Text", nullable)]
     pub description: Option<String>,
     pub deleted_at: Option<DateTimeWithTimeZone>,
     pub user_id: Option<i32>,
     pub is_default: Option<bool>,
+    pub project_id: Option<i32>,
+    pub assignee_id: Option<i32>,
+    pub due_at: Option<DateTimeWithTimeZone>,
+    pub remind_at: Option<DateTimeWithTimeZone>,
+    pub reminded_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::projects::Entity",
+        from = "Column::ProjectId",
+        to = "super::projects::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Projects,
+    #[sea_orm(has_many = "super::task_items::Entity")]
+    TaskItems,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::AssigneeId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Users2,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -26,12 +50,18 @@ pub enum Relation {
         on_update = "NoAction",
         on_delete = "NoAction"
     )]
-    Users,
+    Users1,
+}
+
+impl Related<super::projects::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Projects.def()
+    }
 }
 
-impl Related<super::users::Entity> for Entity {
+impl Related<super::task_items::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Users.def()
+        Relation::TaskItems.def()
     }
 }
 
@@ -10,20 +10,87 @@ pub struct Model {
     #[sea_orm(unique)]
     pub username: String,
     pub password: String,
+    pub role: String,
+    pub verified_at: Option<DateTimeWithTimeZone>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTimeWithTimeZone>,
+    pub totp_last_step: Option<i64>,
     pub deleted_at: Option<DateTimeWithTimeZone>,
-    #[sea_orm(column_type = "Text", nullable)]
-    pub token: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
-    #[sea_orm(has_many = "super::tasks::Entity")]
-    Tasks,
+    #[sea_orm(has_many = "super::access_tokens::Entity")]
+    AccessTokens,
+    #[sea_orm(has_many = "super::identities::Entity")]
+    Identities,
+    #[sea_orm(has_many = "super::one_time_tokens::Entity")]
+    OneTimeTokens,
+    #[sea_orm(has_many = "super::project_invitations::Entity")]
+    ProjectInvitations,
+    #[sea_orm(has_many = "super::project_members::Entity")]
+    ProjectMembers,
+    #[sea_orm(has_many = "super::projects::Entity")]
+    Projects,
+    #[sea_orm(has_many = "super::recovery_codes::Entity")]
+    RecoveryCodes,
+    #[sea_orm(has_many = "super::refresh_tokens::Entity")]
+    RefreshTokens,
+    #[sea_orm(has_many = "super::sessions::Entity")]
+    Sessions,
 }
 
-impl Related<super::tasks::Entity> for Entity {
+impl Related<super::access_tokens::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Tasks.def()
+        Relation::AccessTokens.def()
+    }
+}
+
+impl Related<super::identities::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Identities.def()
+    }
+}
+
+impl Related<super::one_time_tokens::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OneTimeTokens.def()
+    }
+}
+
+impl Related<super::project_invitations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectInvitations.def()
+    }
+}
+
+impl Related<super::project_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectMembers.def()
+    }
+}
+
+impl Related<super::projects::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Projects.def()
+    }
+}
+
+impl Related<super::recovery_codes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCodes.def()
+    }
+}
+
+impl Related<super::refresh_tokens::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RefreshTokens.def()
+    }
+}
+
+impl Related<super::sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sessions.def()
     }
 }
 
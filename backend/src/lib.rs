@@ -1,6 +1,7 @@
 use app_state::AppState;
 use eyre::Result;
 use std::net::SocketAddr;
+use utils::{reminders::spawn_reminder_job, retention::spawn_purge_job};
 
 pub mod app_state;
 mod database;
@@ -8,7 +9,16 @@ mod queires;
 mod routes;
 pub mod utils;
 
+pub use routes::create_routes;
+
 pub async fn run(app_state: AppState) -> Result<()> {
+    spawn_purge_job(
+        app_state.db.clone(),
+        app_state.config.retention.clone(),
+        app_state.login_throttle.clone(),
+    );
+    spawn_reminder_job(app_state.db.clone(), app_state.notifier.clone());
+
     let app = routes::create_routes(app_state).await;
 
     // region: ---Start Server
@@ -16,7 +26,7 @@ pub async fn run(app_state: AppState) -> Result<()> {
     println!("->> LISTENING on http://{addr}\n");
 
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
     // endregion: ---Start Server
     Ok(())
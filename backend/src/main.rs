@@ -1,14 +1,39 @@
+use std::sync::Arc;
+
 use dotenvy::dotenv;
 use eyre::Result;
 use sea_orm::Database;
-use web_app::{app_state::AppState, run, utils::token_wrapper::TokenWrapper};
+use web_app::{
+    app_state::{AppConfig, AppState},
+    run,
+    utils::{
+        auth_cookies::auth_cookie_config_from_env,
+        jwt::{jwt_config_from_env, JwtIssuer},
+        key_ring::key_ring_from_env,
+        login_throttle::login_throttle_from_env,
+        mailer::mailer_from_env,
+        notifier::notifier_from_env,
+        oidc::oidc_providers_from_env,
+        password_hasher::password_hasher_from_env,
+        retention::retention_config_from_env,
+    },
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
     // create app state variables
     let database_url = dotenvy::var("DATABASE_URL")?;
-    let jwt_secret = dotenvy::var("JWT_SECRET")?;
+    let jwt = JwtIssuer {
+        keys: Arc::new(key_ring_from_env()?),
+        config: jwt_config_from_env()?,
+    };
+    let app_url = dotenvy::var("APP_URL")
+        .unwrap_or_else(|_| "http://localhost:8080".to_owned());
+    let mailer = mailer_from_env()?;
+    let notifier = notifier_from_env(&mailer, &app_url)?;
+    let password_hasher = password_hasher_from_env()?;
+    let oidc = oidc_providers_from_env()?;
 
     // connect database
     let db = match Database::connect(database_url).await {
@@ -19,10 +44,22 @@ async fn main() -> Result<()> {
         }
     };
 
+    let login_throttle = login_throttle_from_env(&db)?;
+
     // create Appstate
     let app_state = AppState {
         db,
-        jwt_secret: TokenWrapper(jwt_secret),
+        jwt,
+        mailer,
+        notifier,
+        login_throttle,
+        password_hasher,
+        oidc,
+        config: AppConfig {
+            app_url,
+            auth_cookie: auth_cookie_config_from_env()?,
+            retention: retention_config_from_env()?,
+        },
     };
     run(app_state).await?;
 
@@ -0,0 +1,150 @@
+use crate::{
+    database::access_tokens::{
+        self, Entity as AccessTokens, Model as AccessTokenModel,
+    },
+    utils::{
+        app_error::AppError,
+        scope::Scope,
+        secure_token::{generate_token, hash_token},
+    },
+};
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ActiveModelTrait,
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder, Set,
+};
+
+/// Prefix that tells personal access tokens apart from JWTs.
+pub const ACCESS_TOKEN_PREFIX: &str = "pat_";
+
+/// Stores a new token and returns it together with the raw value, which is
+/// only ever shown to the user once.
+pub async fn create_access_token(
+    db: &DatabaseConnection, user_id: i32, name: String, scopes: &[Scope],
+    expires_at: DateTime<Utc>,
+) -> Result<(AccessTokenModel, String), AppError> {
+    let token = format!("{}{}", ACCESS_TOKEN_PREFIX, generate_token());
+
+    let access_token = access_tokens::ActiveModel {
+        user_id: Set(user_id),
+        name: Set(name),
+        token_hash: Set(hash_token(&token)),
+        scopes: Set(Scope::join(scopes)),
+        expires_at: Set(expires_at.into()),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|error| {
+        eprintln!("Error saving access token: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    })?;
+
+    Ok((access_token, token))
+}
+
+pub async fn find_access_tokens_by_user(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<Vec<AccessTokenModel>, AppError> {
+    AccessTokens::find()
+        .filter(access_tokens::Column::UserId.eq(user_id))
+        .order_by_desc(access_tokens::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting access tokens: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting access tokens",
+            )
+        })
+}
+
+/// Finds the unexpired token with this raw value.
+pub async fn find_active_access_token(
+    db: &DatabaseConnection, token: &str,
+) -> Result<Option<AccessTokenModel>, AppError> {
+    AccessTokens::find()
+        .filter(access_tokens::Column::TokenHash.eq(hash_token(token)))
+        .filter(access_tokens::Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting access token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            )
+        })
+}
+
+/// Records that the token was used. Only written once a minute, so scripts
+/// hammering the API don't turn every read into a write.
+pub async fn touch_access_token(
+    db: &DatabaseConnection, id: i32,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    let stale =
+        now - Duration::try_minutes(1).expect("Failed to create duration");
+
+    AccessTokens::update_many()
+        .col_expr(
+            access_tokens::Column::LastUsedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(now))),
+        )
+        .filter(access_tokens::Column::Id.eq(id))
+        .filter(
+            Condition::any()
+                .add(access_tokens::Column::LastUsedAt.is_null())
+                .add(access_tokens::Column::LastUsedAt.lt(stale)),
+        )
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error updating access token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            )
+        })?;
+
+    Ok(())
+}
+
+pub async fn delete_access_token(
+    db: &DatabaseConnection, access_token: AccessTokenModel,
+) -> Result<(), AppError> {
+    access_token.delete(db).await.map_err(|error| {
+        eprintln!("Error deleting access token: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error deleting access token",
+        )
+    })?;
+
+    Ok(())
+}
+
+pub async fn delete_user_access_tokens(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<(), AppError> {
+    AccessTokens::delete_many()
+        .filter(access_tokens::Column::UserId.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error deleting user access tokens: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error deleting access tokens",
+            )
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use crate::{
+    database::identities::{
+        self, Entity as Identities, Model as IdentityModel,
+    },
+    utils::app_error::AppError,
+};
+use axum::http::StatusCode;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, Set,
+};
+
+pub async fn find_identity(
+    db: &DatabaseConnection, provider: &str, subject: &str,
+) -> Result<Option<IdentityModel>, AppError> {
+    Identities::find()
+        .filter(identities::Column::Provider.eq(provider))
+        .filter(identities::Column::Subject.eq(subject))
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting identity: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })
+}
+
+/// Links the external account to the user, so later logins with the same
+/// provider find them by subject.
+pub async fn create_identity(
+    db: &DatabaseConnection, user_id: i32, provider: &str, subject: &str,
+    email: Option<String>,
+) -> Result<IdentityModel, AppError> {
+    identities::ActiveModel {
+        user_id: Set(user_id),
+        provider: Set(provider.to_owned()),
+        subject: Set(subject.to_owned()),
+        email: Set(email),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|error| {
+        eprintln!("Error saving identity: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    })
+}
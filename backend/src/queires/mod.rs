@@ -1,2 +1,13 @@
+pub mod access_token_queries;
+pub mod identity_queries;
+pub mod one_time_token_queries;
+pub mod project_invitation_queries;
+pub mod project_member_queries;
+pub mod project_queries;
+pub mod recovery_code_queries;
+pub mod refresh_token_queries;
+pub mod revoked_token_queries;
+pub mod session_queries;
+pub mod task_item_queries;
 pub mod task_queries;
 pub mod user_queries;
@@ -0,0 +1,148 @@
+use crate::{
+    database::one_time_tokens::{
+        self, Entity as OneTimeTokens, Model as OneTimeTokenModel,
+    },
+    utils::{
+        app_error::AppError,
+        secure_token::{generate_token, hash_token},
+    },
+};
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ActiveModelTrait,
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+
+/// What a single-use token emailed to a user is allowed to do.
+#[derive(Clone, Copy, Debug)]
+pub enum TokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+    MfaPending,
+}
+
+impl TokenPurpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::VerifyEmail => "verify_email",
+            TokenPurpose::ResetPassword => "reset_password",
+            TokenPurpose::MfaPending => "mfa_pending",
+        }
+    }
+
+    fn lifetime(&self) -> Duration {
+        match self {
+            TokenPurpose::VerifyEmail => Duration::try_hours(24),
+            TokenPurpose::ResetPassword => Duration::try_hours(1),
+            TokenPurpose::MfaPending => Duration::try_minutes(5),
+        }
+        .expect("Failed to create duration")
+    }
+}
+
+/// Issues a new single-use token for the user, invalidating any earlier one
+/// issued for the same purpose. Returns the raw value to be emailed.
+pub async fn create_one_time_token(
+    db: &DatabaseConnection, user_id: i32, purpose: TokenPurpose,
+) -> Result<String, AppError> {
+    let now = Utc::now();
+
+    OneTimeTokens::update_many()
+        .col_expr(
+            one_time_tokens::Column::UsedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(now))),
+        )
+        .filter(one_time_tokens::Column::UserId.eq(user_id))
+        .filter(one_time_tokens::Column::Purpose.eq(purpose.as_str()))
+        .filter(one_time_tokens::Column::UsedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error invalidating one time tokens: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    let token = generate_token();
+
+    one_time_tokens::ActiveModel {
+        user_id: Set(user_id),
+        purpose: Set(purpose.as_str().to_owned()),
+        token_hash: Set(hash_token(&token)),
+        expires_at: Set((now + purpose.lifetime()).into()),
+        created_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|error| {
+        eprintln!("Error saving one time token: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    })?;
+
+    Ok(token)
+}
+
+fn invalid_token() -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, "invalid or expired token")
+}
+
+/// Looks a token up without using it, failing with 400 when it is unknown,
+/// expired, already used or meant for something else.
+pub async fn find_one_time_token(
+    db: &DatabaseConnection, token: &str, purpose: TokenPurpose,
+) -> Result<OneTimeTokenModel, AppError> {
+    OneTimeTokens::find()
+        .filter(one_time_tokens::Column::TokenHash.eq(hash_token(token)))
+        .filter(one_time_tokens::Column::Purpose.eq(purpose.as_str()))
+        .filter(one_time_tokens::Column::UsedAt.is_null())
+        .filter(one_time_tokens::Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting one time token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?
+        .ok_or_else(invalid_token)
+}
+
+/// Marks a token as used and returns it, failing like
+/// [`find_one_time_token`] when it cannot be used.
+pub async fn consume_one_time_token(
+    db: &DatabaseConnection, token: &str, purpose: TokenPurpose,
+) -> Result<OneTimeTokenModel, AppError> {
+    let stored = find_one_time_token(db, token, purpose).await?;
+
+    let result = OneTimeTokens::update_many()
+        .col_expr(
+            one_time_tokens::Column::UsedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+        )
+        .filter(one_time_tokens::Column::Id.eq(stored.id))
+        .filter(one_time_tokens::Column::UsedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error consuming one time token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    // Someone else consumed it between our read and write.
+    if result.rows_affected != 1 {
+        return Err(invalid_token());
+    }
+
+    Ok(stored)
+}
@@ -0,0 +1,128 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ActiveModelTrait,
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set, TryIntoModel,
+};
+
+use crate::{
+    database::project_invitations::{
+        self, Entity as ProjectInvitations, Model as ProjectInvitationModel,
+    },
+    utils::{app_error::AppError, project_role::ProjectRole},
+};
+
+/// How long an invitation can be answered.
+const INVITATION_LIFETIME_DAYS: i64 = 7;
+
+fn invitation_error(action: &'static str) -> impl Fn(DbErr) -> AppError {
+    move |error| {
+        eprintln!("Error {action} project invitations: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    }
+}
+
+/// Only invitations that were neither answered nor expired.
+fn pending() -> Condition {
+    Condition::all()
+        .add(project_invitations::Column::AcceptedAt.is_null())
+        .add(project_invitations::Column::DeclinedAt.is_null())
+        .add(project_invitations::Column::ExpiresAt.gt(Utc::now()))
+}
+
+/// Invites the email address into the project, replacing any earlier
+/// invitation to it that is still pending.
+pub async fn create_project_invitation(
+    db: &DatabaseConnection, project_id: i32, email: &str, role: ProjectRole,
+    invited_by: i32,
+) -> Result<ProjectInvitationModel, AppError> {
+    let email = email.to_lowercase();
+    let now = Utc::now();
+
+    ProjectInvitations::update_many()
+        .col_expr(
+            project_invitations::Column::DeclinedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(now))),
+        )
+        .filter(project_invitations::Column::ProjectId.eq(project_id))
+        .filter(project_invitations::Column::Email.eq(email.as_str()))
+        .filter(pending())
+        .exec(db)
+        .await
+        .map_err(invitation_error("replacing"))?;
+
+    let invitation = project_invitations::ActiveModel {
+        project_id: Set(project_id),
+        email: Set(email),
+        role: Set(role.as_str().to_owned()),
+        invited_by: Set(Some(invited_by)),
+        expires_at: Set((now + Duration::days(INVITATION_LIFETIME_DAYS)).into()),
+        created_at: Set(now.into()),
+        ..Default::default()
+    };
+
+    save_active_invitation(db, invitation).await
+}
+
+pub async fn save_active_invitation(
+    db: &DatabaseConnection, invitation: project_invitations::ActiveModel,
+) -> Result<ProjectInvitationModel, AppError> {
+    invitation
+        .save(db)
+        .await
+        .map_err(invitation_error("saving"))?
+        .try_into_model()
+        .map_err(invitation_error("converting"))
+}
+
+pub async fn find_pending_project_invitations(
+    db: &DatabaseConnection, project_id: i32,
+) -> Result<Vec<ProjectInvitationModel>, AppError> {
+    ProjectInvitations::find()
+        .filter(project_invitations::Column::ProjectId.eq(project_id))
+        .filter(pending())
+        .order_by_desc(project_invitations::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(invitation_error("getting"))
+}
+
+/// The pending invitations sent to the email address.
+pub async fn find_pending_invitations_for(
+    db: &DatabaseConnection, email: &str,
+) -> Result<Vec<ProjectInvitationModel>, AppError> {
+    ProjectInvitations::find()
+        .filter(project_invitations::Column::Email.eq(email.to_lowercase()))
+        .filter(pending())
+        .order_by_desc(project_invitations::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(invitation_error("getting"))
+}
+
+/// Finds a pending invitation, 404 once it was answered or expired.
+pub async fn find_pending_invitation(
+    db: &DatabaseConnection, id: i32,
+) -> Result<ProjectInvitationModel, AppError> {
+    ProjectInvitations::find_by_id(id)
+        .filter(pending())
+        .one(db)
+        .await
+        .map_err(invitation_error("getting"))?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))
+}
+
+pub async fn delete_project_invitation(
+    db: &DatabaseConnection, invitation: ProjectInvitationModel,
+) -> Result<(), AppError> {
+    ProjectInvitations::delete_by_id(invitation.id)
+        .exec(db)
+        .await
+        .map_err(invitation_error("deleting"))?;
+
+    Ok(())
+}
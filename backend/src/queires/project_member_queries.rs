@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use sea_orm::{
+    sea_query::SelectStatement, ActiveModelTrait, ColumnTrait,
+    DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait, Set, TryIntoModel,
+};
+
+use crate::{
+    database::{
+        project_members::{
+            self, Entity as ProjectMembers, Model as ProjectMemberModel,
+        },
+        projects::Model as ProjectModel,
+    },
+    utils::{app_error::AppError, project_role::ProjectRole},
+};
+
+fn member_error(action: &'static str) -> impl Fn(DbErr) -> AppError {
+    move |error| {
+        eprintln!("Error {action} project members: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    }
+}
+
+/// Ids of the projects the user was let into, for use as a subquery.
+pub fn member_project_ids(user_id: i32) -> SelectStatement {
+    ProjectMembers::find()
+        .select_only()
+        .column(project_members::Column::ProjectId)
+        .filter(project_members::Column::UserId.eq(user_id))
+        .into_query()
+}
+
+pub async fn find_membership(
+    db: &DatabaseConnection, project_id: i32, user_id: i32,
+) -> Result<Option<ProjectMemberModel>, AppError> {
+    ProjectMembers::find()
+        .filter(project_members::Column::ProjectId.eq(project_id))
+        .filter(project_members::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(member_error("getting"))
+}
+
+/// The user's role in the project, `None` when they are not in it.
+pub async fn find_project_role(
+    db: &DatabaseConnection, project: &ProjectModel, user_id: i32,
+) -> Result<Option<ProjectRole>, AppError> {
+    if project.user_id == user_id {
+        return Ok(Some(ProjectRole::Owner));
+    }
+
+    Ok(find_membership(db, project.id, user_id)
+        .await?
+        .map(|member| ProjectRole::parse(&member.role)))
+}
+
+/// The user's role in each project they were let into.
+pub async fn find_member_roles(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<HashMap<i32, ProjectRole>, AppError> {
+    let memberships = ProjectMembers::find()
+        .filter(project_members::Column::UserId.eq(user_id))
+        .all(db)
+        .await
+        .map_err(member_error("getting"))?;
+
+    Ok(memberships
+        .into_iter()
+        .map(|member| (member.project_id, ProjectRole::parse(&member.role)))
+        .collect())
+}
+
+pub async fn find_project_members(
+    db: &DatabaseConnection, project_id: i32,
+) -> Result<Vec<ProjectMemberModel>, AppError> {
+    ProjectMembers::find()
+        .filter(project_members::Column::ProjectId.eq(project_id))
+        .order_by_asc(project_members::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(member_error("getting"))
+}
+
+/// Lets the user into the project, or changes their role when they are
+/// already in it.
+pub async fn set_project_member(
+    db: &DatabaseConnection, project_id: i32, user_id: i32, role: ProjectRole,
+) -> Result<ProjectMemberModel, AppError> {
+    let member = match find_membership(db, project_id, user_id).await? {
+        Some(member) => {
+            let mut member: project_members::ActiveModel = member.into();
+            member.role = Set(role.as_str().to_owned());
+            member
+        }
+        None => project_members::ActiveModel {
+            project_id: Set(project_id),
+            user_id: Set(user_id),
+            role: Set(role.as_str().to_owned()),
+            ..Default::default()
+        },
+    };
+
+    member
+        .save(db)
+        .await
+        .map_err(member_error("saving"))?
+        .try_into_model()
+        .map_err(member_error("converting"))
+}
+
+pub async fn delete_project_member(
+    db: &DatabaseConnection, member: ProjectMemberModel,
+) -> Result<(), AppError> {
+    ProjectMembers::delete_by_id(member.id)
+        .exec(db)
+        .await
+        .map_err(member_error("deleting"))?;
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use axum::http::StatusCode;
+use sea_orm::{
+    sea_query::{Expr, SelectStatement},
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, Set,
+    TryIntoModel,
+};
+
+use crate::{
+    database::{
+        projects::{self, Entity as Projects, Model as ProjectModel},
+        tasks::{self, Entity as Tasks},
+    },
+    queires::project_member_queries::member_project_ids,
+    utils::app_error::AppError,
+};
+
+/// Name of the project every user starts with, new tasks land here unless
+/// they are given another project.
+pub const INBOX_NAME: &str = "Inbox";
+
+fn project_error(action: &'static str) -> impl Fn(DbErr) -> AppError {
+    move |error| {
+        eprintln!("Error {action} project: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    }
+}
+
+/// Ids of the projects the user owns, for use as a subquery.
+pub fn owned_project_ids(user_id: i32) -> SelectStatement {
+    Projects::find()
+        .select_only()
+        .column(projects::Column::Id)
+        .filter(projects::Column::UserId.eq(user_id))
+        .into_query()
+}
+
+pub async fn save_active_project(
+    db: &DatabaseConnection, project: projects::ActiveModel,
+) -> Result<ProjectModel, AppError> {
+    project
+        .save(db)
+        .await
+        .map_err(project_error("saving"))?
+        .try_into_model()
+        .map_err(project_error("converting"))
+}
+
+pub async fn create_inbox(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<ProjectModel, AppError> {
+    let inbox = projects::ActiveModel {
+        user_id: Set(user_id),
+        name: Set(INBOX_NAME.to_owned()),
+        is_inbox: Set(true),
+        ..Default::default()
+    };
+
+    save_active_project(db, inbox).await
+}
+
+/// The user's inbox, created on the fly for accounts that predate projects.
+pub async fn find_or_create_inbox(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<ProjectModel, AppError> {
+    let inbox = Projects::find()
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::IsInbox.eq(true))
+        .one(db)
+        .await
+        .map_err(project_error("getting"))?;
+
+    match inbox {
+        Some(inbox) => Ok(inbox),
+        None => create_inbox(db, user_id).await,
+    }
+}
+
+/// The projects the user owns or was let into.
+pub async fn find_projects_by_user(
+    db: &DatabaseConnection, user_id: i32, include_archived: bool,
+) -> Result<Vec<ProjectModel>, AppError> {
+    let mut query = Projects::find().filter(
+        Condition::any()
+            .add(projects::Column::UserId.eq(user_id))
+            .add(projects::Column::Id.in_subquery(member_project_ids(user_id))),
+    );
+
+    if !include_archived {
+        query = query.filter(projects::Column::Archived.eq(false));
+    }
+
+    // The user's own inbox always comes first, inboxes can't be shared.
+    query
+        .order_by_desc(projects::Column::IsInbox)
+        .order_by_asc(projects::Column::Name)
+        .order_by_asc(projects::Column::Id)
+        .all(db)
+        .await
+        .map_err(project_error("getting"))
+}
+
+pub async fn find_project_by_id(
+    db: &DatabaseConnection, id: i32,
+) -> Result<ProjectModel, AppError> {
+    Projects::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(project_error("getting"))?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))
+}
+
+/// Deletes the project, moving its tasks to the owner's inbox so none of
+/// them are lost with it.
+pub async fn delete_project(
+    db: &DatabaseConnection, project: ProjectModel,
+) -> Result<(), AppError> {
+    let inbox = find_or_create_inbox(db, project.user_id).await?;
+
+    Tasks::update_many()
+        .col_expr(tasks::Column::ProjectId, Expr::value(Some(inbox.id)))
+        .filter(tasks::Column::ProjectId.eq(Some(project.id)))
+        .exec(db)
+        .await
+        .map_err(project_error("moving tasks out of"))?;
+
+    Projects::delete_by_id(project.id)
+        .exec(db)
+        .await
+        .map_err(project_error("deleting"))?;
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use crate::{
+    database::recovery_codes::{self, Entity as RecoveryCodes},
+    utils::{app_error::AppError, secure_token::hash_token},
+};
+use axum::http::StatusCode;
+use chrono::Utc;
+use rand::{rngs::OsRng, RngCore};
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ColumnTrait,
+    DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Codes are shown as `xxxxx-xxxxx` but compared without the dash or case.
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; 5];
+    OsRng.fill_bytes(&mut bytes);
+    let code = hex::encode(bytes);
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+/// Replaces any codes the user had with a new set and returns the raw
+/// values, which are only shown to the user this once.
+pub async fn replace_recovery_codes(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<Vec<String>, AppError> {
+    delete_recovery_codes(db, user_id).await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+
+    let models = codes.iter().map(|code| recovery_codes::ActiveModel {
+        user_id: Set(user_id),
+        code_hash: Set(hash_token(&normalize_code(code))),
+        ..Default::default()
+    });
+
+    RecoveryCodes::insert_many(models)
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error saving recovery codes: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(codes)
+}
+
+/// Marks one of the user's unused codes as used, returning whether the code
+/// was valid.
+pub async fn use_recovery_code(
+    db: &DatabaseConnection, user_id: i32, code: &str,
+) -> Result<bool, AppError> {
+    let result = RecoveryCodes::update_many()
+        .col_expr(
+            recovery_codes::Column::UsedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+        )
+        .filter(recovery_codes::Column::UserId.eq(user_id))
+        .filter(
+            recovery_codes::Column::CodeHash
+                .eq(hash_token(&normalize_code(code))),
+        )
+        .filter(recovery_codes::Column::UsedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error using recovery code: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(result.rows_affected > 0)
+}
+
+pub async fn delete_recovery_codes(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<(), AppError> {
+    RecoveryCodes::delete_many()
+        .filter(recovery_codes::Column::UserId.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error deleting recovery codes: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use crate::{
+    database::refresh_tokens::{
+        self, Entity as RefreshTokens, Model as RefreshTokenModel,
+    },
+    utils::{
+        app_error::AppError,
+        secure_token::{generate_token, hash_token},
+    },
+};
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ActiveModelTrait,
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+
+const REFRESH_TOKEN_DAYS: i64 = 30;
+
+/// Stores a new refresh token for the session and returns the raw value,
+/// which is only ever seen by the client. Passing `None` starts a new family.
+pub async fn create_refresh_token(
+    db: &DatabaseConnection, user_id: i32, session_id: i32,
+    family_id: Option<String>,
+) -> Result<String, AppError> {
+    let token = generate_token();
+    let now = Utc::now();
+    let expires_at = now
+        + Duration::try_days(REFRESH_TOKEN_DAYS)
+            .expect("Failed to create duration");
+
+    refresh_tokens::ActiveModel {
+        user_id: Set(user_id),
+        session_id: Set(Some(session_id)),
+        family_id: Set(family_id.unwrap_or_else(generate_token)),
+        token_hash: Set(hash_token(&token)),
+        expires_at: Set(expires_at.into()),
+        created_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|error| {
+        eprintln!("Error saving refresh token: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    })?;
+
+    Ok(token)
+}
+
+pub async fn find_refresh_token(
+    db: &DatabaseConnection, token: &str,
+) -> Result<RefreshTokenModel, AppError> {
+    RefreshTokens::find()
+        .filter(refresh_tokens::Column::TokenHash.eq(hash_token(token)))
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting refresh token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::UNAUTHORIZED, "invalid refresh token")
+        })
+}
+
+/// Marks a single refresh token as used. Returns `false` when another
+/// request already revoked it, which callers must treat as reuse.
+pub async fn revoke_refresh_token(
+    db: &DatabaseConnection, id: i32,
+) -> Result<bool, AppError> {
+    let result = RefreshTokens::update_many()
+        .col_expr(
+            refresh_tokens::Column::RevokedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+        )
+        .filter(refresh_tokens::Column::Id.eq(id))
+        .filter(refresh_tokens::Column::RevokedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error revoking refresh token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(result.rows_affected == 1)
+}
+
+pub async fn revoke_token_family(
+    db: &DatabaseConnection, family_id: &str,
+) -> Result<(), AppError> {
+    RefreshTokens::update_many()
+        .col_expr(
+            refresh_tokens::Column::RevokedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+        )
+        .filter(refresh_tokens::Column::FamilyId.eq(family_id))
+        .filter(refresh_tokens::Column::RevokedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error revoking refresh token family: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(())
+}
+
+pub async fn revoke_user_refresh_tokens(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<(), AppError> {
+    RefreshTokens::update_many()
+        .col_expr(
+            refresh_tokens::Column::RevokedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+        )
+        .filter(refresh_tokens::Column::UserId.eq(user_id))
+        .filter(refresh_tokens::Column::RevokedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error revoking user refresh tokens: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Revokes every refresh token of the user but those of the session making
+/// the request, e.g. after a password change.
+pub async fn revoke_other_refresh_tokens(
+    db: &DatabaseConnection, user_id: i32, current_session_id: Option<i32>,
+) -> Result<(), AppError> {
+    let mut other_sessions =
+        Condition::any().add(refresh_tokens::Column::SessionId.is_null());
+    if let Some(session_id) = current_session_id {
+        other_sessions = other_sessions
+            .add(refresh_tokens::Column::SessionId.ne(session_id));
+    }
+
+    RefreshTokens::update_many()
+        .col_expr(
+            refresh_tokens::Column::RevokedAt,
+            Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+        )
+        .filter(refresh_tokens::Column::UserId.eq(user_id))
+        .filter(refresh_tokens::Column::RevokedAt.is_null())
+        .filter(other_sessions)
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error revoking user refresh tokens: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(())
+}
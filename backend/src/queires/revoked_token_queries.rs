@@ -0,0 +1,68 @@
+use crate::{
+    database::revoked_tokens::{self, Entity as RevokedTokens},
+    utils::app_error::AppError,
+};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, Set,
+};
+
+/// Adds an access token id to the denylist until the token would have
+/// expired on its own. Expired entries are swept on the way in so the table
+/// only ever holds tokens that are still cryptographically valid.
+pub async fn revoke_token(
+    db: &DatabaseConnection, jti: &str, expires_at: DateTime<Utc>,
+) -> Result<(), AppError> {
+    RevokedTokens::delete_many()
+        .filter(revoked_tokens::Column::ExpiresAt.lt(Utc::now()))
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error sweeping revoked tokens: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            )
+        })?;
+
+    RevokedTokens::insert(revoked_tokens::ActiveModel {
+        jti: Set(jti.to_owned()),
+        expires_at: Set(expires_at.into()),
+    })
+    .on_conflict(
+        OnConflict::column(revoked_tokens::Column::Jti)
+            .do_nothing()
+            .to_owned(),
+    )
+    .do_nothing()
+    .exec(db)
+    .await
+    .map_err(|error| {
+        eprintln!("Error revoking token: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal server error",
+        )
+    })?;
+
+    Ok(())
+}
+
+pub async fn is_token_revoked(
+    db: &DatabaseConnection, jti: &str,
+) -> Result<bool, AppError> {
+    let revoked = RevokedTokens::find_by_id(jti.to_owned())
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error checking revoked token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            )
+        })?;
+
+    Ok(revoked.is_some())
+}
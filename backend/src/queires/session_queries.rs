@@ -0,0 +1,211 @@
+use crate::{
+    database::sessions::{self, Entity as Sessions, Model as SessionModel},
+    queires::revoked_token_queries::revoke_token,
+    utils::{app_error::AppError, client_info::ClientInfo},
+};
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ActiveModelTrait,
+    ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, ModelTrait,
+    QueryFilter, QueryOrder, Set,
+};
+
+pub const SESSION_DAYS: i64 = 30;
+
+fn session_expiry() -> chrono::DateTime<Utc> {
+    Utc::now()
+        + Duration::try_days(SESSION_DAYS).expect("Failed to create duration")
+}
+
+pub async fn create_session(
+    db: &DatabaseConnection, user_id: i32, jti: &str, client: &ClientInfo,
+) -> Result<SessionModel, AppError> {
+    let now = Utc::now();
+
+    sessions::ActiveModel {
+        user_id: Set(user_id),
+        jti: Set(jti.to_owned()),
+        user_agent: Set(client.user_agent.clone()),
+        ip: Set(client.ip.clone()),
+        created_at: Set(now.into()),
+        last_seen_at: Set(now.into()),
+        expires_at: Set(session_expiry().into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|error| {
+        eprintln!("Error creating session: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    })
+}
+
+pub async fn find_session_by_jti(
+    db: &DatabaseConnection, jti: &str,
+) -> Result<Option<SessionModel>, AppError> {
+    Sessions::find()
+        .filter(sessions::Column::Jti.eq(jti))
+        .filter(sessions::Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting session by jti: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            )
+        })
+}
+
+pub async fn find_session_by_id(
+    db: &DatabaseConnection, id: i32,
+) -> Result<Option<SessionModel>, AppError> {
+    Sessions::find_by_id(id)
+        .filter(sessions::Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting session by id: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            )
+        })
+}
+
+pub async fn find_sessions_by_user(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<Vec<SessionModel>, AppError> {
+    Sessions::find()
+        .filter(sessions::Column::UserId.eq(user_id))
+        .filter(sessions::Column::ExpiresAt.gt(Utc::now()))
+        .order_by_desc(sessions::Column::LastSeenAt)
+        .all(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting sessions: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting sessions",
+            )
+        })
+}
+
+/// Records that the session made a request. Only written once a minute so
+/// busy clients do not update the row on every request.
+pub async fn touch_session(
+    db: &DatabaseConnection, jti: &str,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    let stale =
+        now - Duration::try_minutes(1).expect("Failed to create duration");
+
+    Sessions::update_many()
+        .col_expr(
+            sessions::Column::LastSeenAt,
+            Expr::value(DateTimeWithTimeZone::from(now)),
+        )
+        .filter(sessions::Column::Jti.eq(jti))
+        .filter(sessions::Column::LastSeenAt.lt(stale))
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error updating session: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Points an existing session at a freshly issued access token and pushes
+/// its expiry forward, used when a refresh token is rotated. The access token
+/// it replaces goes on the denylist.
+pub async fn rotate_session_token(
+    db: &DatabaseConnection, session: SessionModel, jti: &str,
+) -> Result<SessionModel, AppError> {
+    revoke_token(db, &session.jti, session.expires_at.into()).await?;
+
+    let mut session = session.into_active_model();
+    session.jti = Set(jti.to_owned());
+    session.last_seen_at = Set(Utc::now().into());
+    session.expires_at = Set(session_expiry().into());
+
+    session.update(db).await.map_err(|error| {
+        eprintln!("Error updating session: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal server error",
+        )
+    })
+}
+
+/// Ends a session: its current access token goes on the denylist and the
+/// row is removed, which detaches its refresh tokens.
+pub async fn revoke_session(
+    db: &DatabaseConnection, session: SessionModel,
+) -> Result<(), AppError> {
+    // The access token TTL is capped at `SESSION_DAYS`, so the session
+    // outlives any access token issued for it and denying the jti until then
+    // covers the token however long it was valid for.
+    revoke_token(db, &session.jti, session.expires_at.into()).await?;
+
+    session.delete(db).await.map_err(|error| {
+        eprintln!("Error deleting session: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error deleting session",
+        )
+    })?;
+
+    Ok(())
+}
+
+pub async fn delete_session(
+    db: &DatabaseConnection, id: i32, user_id: i32,
+) -> Result<(), AppError> {
+    let session = Sessions::find_by_id(id)
+        .filter(sessions::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting session by id: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error deleting session",
+            )
+        })?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))?;
+
+    revoke_session(db, session).await
+}
+
+/// Signs the user out everywhere, e.g. after their password changed.
+pub async fn revoke_all_sessions(
+    db: &DatabaseConnection, user_id: i32,
+) -> Result<(), AppError> {
+    for session in find_sessions_by_user(db, user_id).await? {
+        revoke_session(db, session).await?;
+    }
+
+    Ok(())
+}
+
+/// Signs the user out on every device but the one making the request.
+pub async fn revoke_other_sessions(
+    db: &DatabaseConnection, user_id: i32, current_jti: &str,
+) -> Result<(), AppError> {
+    for session in find_sessions_by_user(db, user_id).await? {
+        if session.jti != current_jti {
+            revoke_session(db, session).await?;
+        }
+    }
+
+    Ok(())
+}
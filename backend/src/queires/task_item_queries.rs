@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, SelectStatement, SimpleExpr},
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, TryIntoModel,
+};
+use serde::Serialize;
+
+use crate::{
+    database::task_items::{self, Entity as TaskItems, Model as TaskItemModel},
+    utils::app_error::AppError,
+};
+
+/// How many of a task's items are done.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ItemProgress {
+    pub done: u64,
+    pub total: u64,
+}
+
+fn item_error(action: &'static str) -> impl Fn(DbErr) -> AppError {
+    move |error| {
+        eprintln!("Error {action} task items: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "There was an error, please try again later",
+        )
+    }
+}
+
+pub async fn find_task_items(
+    db: &DatabaseConnection, task_id: i32,
+) -> Result<Vec<TaskItemModel>, AppError> {
+    TaskItems::find()
+        .filter(task_items::Column::TaskId.eq(task_id))
+        .filter(task_items::Column::DeletedAt.is_null())
+        .order_by_asc(task_items::Column::Position)
+        .order_by_asc(task_items::Column::Id)
+        .all(db)
+        .await
+        .map_err(item_error("getting"))
+}
+
+/// Finds an item of the task, 404 when it belongs to another one.
+pub async fn find_task_item(
+    db: &DatabaseConnection, task_id: i32, item_id: i32,
+) -> Result<TaskItemModel, AppError> {
+    TaskItems::find_by_id(item_id)
+        .filter(task_items::Column::TaskId.eq(task_id))
+        .filter(task_items::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(item_error("getting"))?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))
+}
+
+pub async fn save_active_task_item(
+    db: &DatabaseConnection, item: task_items::ActiveModel,
+) -> Result<TaskItemModel, AppError> {
+    item.save(db)
+        .await
+        .map_err(item_error("saving"))?
+        .try_into_model()
+        .map_err(item_error("converting"))
+}
+
+pub async fn delete_task_item(
+    db: &DatabaseConnection, item: TaskItemModel,
+) -> Result<(), AppError> {
+    TaskItems::delete_by_id(item.id)
+        .exec(db)
+        .await
+        .map_err(item_error("deleting"))?;
+
+    Ok(())
+}
+
+/// The completion roll-up of each task, tasks without items are missing.
+pub async fn find_item_progress(
+    db: &DatabaseConnection, task_ids: &[i32],
+) -> Result<HashMap<i32, ItemProgress>, AppError> {
+    if task_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows: Vec<(i32, i64, i64)> = TaskItems::find()
+        .select_only()
+        .column(task_items::Column::TaskId)
+        .column_as(task_items::Column::Id.count(), "total")
+        .column_as(task_items::Column::CompletedAt.count(), "done")
+        .filter(task_items::Column::TaskId.is_in(task_ids.to_vec()))
+        .filter(task_items::Column::DeletedAt.is_null())
+        .group_by(task_items::Column::TaskId)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(item_error("counting"))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(task_id, total, done)| {
+            let progress = ItemProgress {
+                done: done as u64,
+                total: total as u64,
+            };
+            (task_id, progress)
+        })
+        .collect())
+}
+
+/// Moves the task's items to the trash along with it, tagged with the same
+/// time so [`restore_task_items`] brings back exactly these.
+pub async fn soft_delete_task_items(
+    db: &DatabaseConnection, task_id: i32, deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    soft_delete_items(db, task_items::Column::TaskId.eq(task_id), deleted_at)
+        .await
+}
+
+pub async fn restore_task_items(
+    db: &DatabaseConnection, task_id: i32, deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    restore_items(db, task_items::Column::TaskId.eq(task_id), deleted_at).await
+}
+
+/// Like [`soft_delete_task_items`] for every task `task_ids` selects, e.g.
+/// the tasks of a deleted account.
+pub async fn soft_delete_items_of_tasks(
+    db: &DatabaseConnection, task_ids: SelectStatement,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    soft_delete_items(
+        db,
+        task_items::Column::TaskId.in_subquery(task_ids),
+        deleted_at,
+    )
+    .await
+}
+
+pub async fn restore_items_of_tasks(
+    db: &DatabaseConnection, task_ids: SelectStatement,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    restore_items(
+        db,
+        task_items::Column::TaskId.in_subquery(task_ids),
+        deleted_at,
+    )
+    .await
+}
+
+/// Keeps the items in step when a task's `deleted_at` is set directly, e.g.
+/// through PATCH or PUT, like `DELETE /tasks/:id` and the restore route do.
+pub async fn sync_task_items_deleted_at(
+    db: &DatabaseConnection, task_id: i32,
+    previous: Option<DateTimeWithTimeZone>,
+    deleted_at: Option<DateTimeWithTimeZone>,
+) -> Result<(), AppError> {
+    match (previous, deleted_at) {
+        (None, Some(deleted_at)) => {
+            soft_delete_task_items(db, task_id, deleted_at).await
+        }
+        (Some(previous), None) => {
+            restore_task_items(db, task_id, previous).await
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn soft_delete_items(
+    db: &DatabaseConnection, tasks: SimpleExpr,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    TaskItems::update_many()
+        .col_expr(task_items::Column::DeletedAt, Expr::value(Some(deleted_at)))
+        .filter(tasks)
+        .filter(task_items::Column::DeletedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(item_error("deleting"))?;
+
+    Ok(())
+}
+
+async fn restore_items(
+    db: &DatabaseConnection, tasks: SimpleExpr,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    TaskItems::update_many()
+        .col_expr(
+            task_items::Column::DeletedAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .filter(tasks)
+        .filter(task_items::Column::DeletedAt.gte(deleted_at))
+        .exec(db)
+        .await
+        .map_err(item_error("restoring"))?;
+
+    Ok(())
+}
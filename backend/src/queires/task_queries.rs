@@ -1,26 +1,43 @@
 use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
-    QueryFilter, Set, TryIntoModel,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{extension::postgres::PgExpr, Expr, NullOrdering},
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
+    Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait,
+    Select, Set, TryIntoModel,
 };
 
 use crate::{
     database::{
+        projects::{self, Entity as Projects},
         tasks::{self, Entity as Tasks, Model as TaskModel},
-        users::Model as UserModel,
+        users::{self, Entity as Users, Model as UserModel},
+    },
+    queires::{
+        project_member_queries::member_project_ids,
+        project_queries::owned_project_ids,
+        task_item_queries::{
+            restore_items_of_tasks, soft_delete_items_of_tasks,
+        },
     },
     routes::create_task::ValidateCreateTask,
-    utils::app_error::AppError,
+    utils::{app_error::AppError, task_status::TaskStatus},
 };
 
 pub async fn create_task(
-    task: ValidateCreateTask, user: &UserModel, db: &DatabaseConnection,
+    task: ValidateCreateTask, user: &UserModel, project_id: i32,
+    db: &DatabaseConnection,
 ) -> Result<TaskModel, AppError> {
     let new_task = tasks::ActiveModel {
         title: Set(task.title.unwrap()),
         priority: Set(task.priority),
         description: Set(task.description),
         user_id: Set(Some(user.id)),
+        project_id: Set(Some(project_id)),
+        due_at: Set(task.due_at),
+        remind_at: Set(task.remind_at),
+        status: Set(TaskStatus::Todo.as_str().to_owned()),
         ..Default::default()
     };
 
@@ -38,6 +55,40 @@ pub async fn save_active_task(
     convert_active_to_model(task)
 }
 
+/// Moves the task to another status, stamping or clearing `completed_at` to
+/// match. Fails with 409 when the transition is not allowed, staying in the
+/// current status is always fine.
+pub fn change_status(
+    task: &mut tasks::ActiveModel, current: TaskStatus, next: TaskStatus,
+) -> Result<(), AppError> {
+    if current == next {
+        return Ok(());
+    }
+
+    if !current.can_move_to(next) {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            format!(
+                "a task cannot go from {} to {}",
+                current.as_str(),
+                next.as_str()
+            ),
+        ));
+    }
+
+    task.status = Set(next.as_str().to_owned());
+    match next {
+        TaskStatus::Done => task.completed_at = Set(Some(Utc::now().into())),
+        TaskStatus::Todo | TaskStatus::InProgress => {
+            task.completed_at = Set(None)
+        }
+        // Archiving keeps whether and when the task was completed.
+        TaskStatus::Archived => {}
+    }
+
+    Ok(())
+}
+
 fn convert_active_to_model(
     active_task: tasks::ActiveModel,
 ) -> Result<TaskModel, AppError> {
@@ -54,7 +105,8 @@ pub async fn find_task_by_id(
     db: &DatabaseConnection, id: i32, user_id: i32,
 ) -> Result<TaskModel, AppError> {
     let task = Tasks::find_by_id(id)
-        .filter(tasks::Column::UserId.eq(Some(user_id)))
+        .filter(visible_to(user_id))
+        .filter(tasks::Column::DeletedAt.is_null())
         .one(db)
         .await
         .map_err(|error| {
@@ -71,21 +123,486 @@ pub async fn find_task_by_id(
     })
 }
 
-pub async fn find_all_tasks(
-    db: &DatabaseConnection, user_id: i32, get_deleted: bool,
-) -> Result<Vec<TaskModel>, AppError> {
-    let mut query =
-        Tasks::find().filter(tasks::Column::UserId.eq(Some(user_id)));
+/// Who a listed task has to be assigned to.
+#[derive(Clone, Copy, Debug)]
+pub enum TaskAssignee {
+    User(i32),
+    Unassigned,
+}
+
+/// Which of a user's tasks to list. Unset fields do not filter.
+#[derive(Clone, Debug, Default)]
+pub struct TaskFilter {
+    pub priority: Option<String>,
+    pub assignee: Option<TaskAssignee>,
+    pub status: Option<TaskStatus>,
+    pub completed: Option<bool>,
+    /// Matched case-insensitively against the title and description.
+    pub search: Option<String>,
+    pub completed_after: Option<DateTimeWithTimeZone>,
+    pub completed_before: Option<DateTimeWithTimeZone>,
+    /// Open tasks past their due date, or with `false` everything else.
+    pub overdue: Option<bool>,
+    pub due_before: Option<DateTimeWithTimeZone>,
+    /// Lists the tasks in the trash instead of the live ones.
+    pub deleted: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum TaskSortField {
+    Id,
+    Title,
+    Priority,
+    CompletedAt,
+    DueAt,
+    DeletedAt,
+}
+
+impl TaskSortField {
+    pub fn parse(field: &str) -> Option<Self> {
+        match field {
+            "id" => Some(TaskSortField::Id),
+            "title" => Some(TaskSortField::Title),
+            "priority" => Some(TaskSortField::Priority),
+            "completed_at" => Some(TaskSortField::CompletedAt),
+            "due_at" => Some(TaskSortField::DueAt),
+            "deleted_at" => Some(TaskSortField::DeletedAt),
+            _ => None,
+        }
+    }
+
+    fn column(&self) -> tasks::Column {
+        match self {
+            TaskSortField::Id => tasks::Column::Id,
+            TaskSortField::Title => tasks::Column::Title,
+            TaskSortField::Priority => tasks::Column::Priority,
+            TaskSortField::CompletedAt => tasks::Column::CompletedAt,
+            TaskSortField::DueAt => tasks::Column::DueAt,
+            TaskSortField::DeletedAt => tasks::Column::DeletedAt,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TaskSort {
+    pub field: TaskSortField,
+    pub descending: bool,
+}
+
+/// One page of tasks along with how many match the filter overall.
+pub struct TaskPage {
+    pub tasks: Vec<TaskModel>,
+    pub total: u64,
+}
+
+/// Escapes the `LIKE` wildcards so a search matches the text literally.
+fn like_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{escaped}%")
+}
+
+/// Tasks still waiting to be done, the ones that can be overdue or remind
+/// someone.
+fn open_tasks() -> Condition {
+    Condition::all().add(
+        tasks::Column::Status.is_in([
+            TaskStatus::Todo.as_str(),
+            TaskStatus::InProgress.as_str(),
+        ]),
+    )
+}
+
+fn overdue(now: DateTimeWithTimeZone) -> Condition {
+    open_tasks().add(tasks::Column::DueAt.lt(now))
+}
+
+/// Applies the filter to any query over tasks, so views with other scopes
+/// can reuse it.
+pub fn filter_tasks(
+    mut query: Select<Tasks>, filter: &TaskFilter,
+) -> Select<Tasks> {
+    query = match filter.deleted {
+        true => query.filter(tasks::Column::DeletedAt.is_not_null()),
+        false => query.filter(tasks::Column::DeletedAt.is_null()),
+    };
+
+    if let Some(priority) = &filter.priority {
+        query = query.filter(tasks::Column::Priority.eq(priority.as_str()));
+    }
+
+    if let Some(status) = filter.status {
+        query = query.filter(tasks::Column::Status.eq(status.as_str()));
+    }
+
+    query = match filter.assignee {
+        Some(TaskAssignee::User(user_id)) => {
+            query.filter(tasks::Column::AssigneeId.eq(Some(user_id)))
+        }
+        Some(TaskAssignee::Unassigned) => {
+            query.filter(tasks::Column::AssigneeId.is_null())
+        }
+        None => query,
+    };
+
+    query = match filter.completed {
+        Some(true) => query.filter(tasks::Column::CompletedAt.is_not_null()),
+        Some(false) => query.filter(tasks::Column::CompletedAt.is_null()),
+        None => query,
+    };
+
+    if let Some(search) = filter.search.as_deref().map(str::trim) {
+        if !search.is_empty() {
+            let pattern = like_pattern(search);
+            query = query.filter(
+                Condition::any()
+                    .add(Expr::col(tasks::Column::Title).ilike(&pattern))
+                    .add(Expr::col(tasks::Column::Description).ilike(&pattern)),
+            );
+        }
+    }
+
+    if let Some(completed_after) = filter.completed_after {
+        query = query.filter(tasks::Column::CompletedAt.gte(completed_after));
+    }
 
-    if !get_deleted {
-        query = query.filter(tasks::Column::DeletedAt.is_null());
+    if let Some(completed_before) = filter.completed_before {
+        query = query.filter(tasks::Column::CompletedAt.lt(completed_before));
     }
 
-    query.all(db).await.map_err(|error| {
-        eprintln!("Error getting all tasks: {:?}", error);
+    let now: DateTimeWithTimeZone = Utc::now().into();
+    query = match filter.overdue {
+        Some(true) => query.filter(overdue(now)),
+        // Spelled out since `NOT` would drop the tasks without a due date.
+        Some(false) => query.filter(
+            Condition::any()
+                .add(tasks::Column::DueAt.is_null())
+                .add(overdue(now).not()),
+        ),
+        None => query,
+    };
+
+    if let Some(due_before) = filter.due_before {
+        query = query.filter(tasks::Column::DueAt.lt(due_before));
+    }
+
+    query
+}
+
+/// Sorts and paginates a filtered query. The id always breaks ties so pages
+/// neither skip nor repeat rows.
+pub async fn find_task_page(
+    db: &DatabaseConnection, query: Select<Tasks>, sort: &[TaskSort],
+    offset: u64, limit: u64,
+) -> Result<TaskPage, AppError> {
+    let total = query.clone().count(db).await.map_err(|error| {
+        eprintln!("Error counting tasks: {:?}", error);
         AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
             "Error getting all tasks",
         )
-    })
+    })?;
+
+    let mut query = query;
+    for sort in sort {
+        let order = match sort.descending {
+            true => Order::Desc,
+            false => Order::Asc,
+        };
+        // Tasks without a priority or date go last either way.
+        query = query.order_by_with_nulls(
+            sort.field.column(),
+            order,
+            NullOrdering::Last,
+        );
+    }
+
+    let tasks = query
+        .order_by_asc(tasks::Column::Id)
+        .offset(offset)
+        .limit(limit)
+        .all(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting all tasks: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting all tasks",
+            )
+        })?;
+
+    Ok(TaskPage { tasks, total })
+}
+
+pub async fn find_tasks(
+    db: &DatabaseConnection, user_id: i32, filter: &TaskFilter,
+    sort: &[TaskSort], offset: u64, limit: u64,
+) -> Result<TaskPage, AppError> {
+    let query = filter_tasks(Tasks::find().filter(visible_to(user_id)), filter);
+
+    find_task_page(db, query, sort, offset, limit).await
+}
+
+pub async fn find_project_tasks(
+    db: &DatabaseConnection, project_id: i32, filter: &TaskFilter,
+    sort: &[TaskSort], offset: u64, limit: u64,
+) -> Result<TaskPage, AppError> {
+    let query = filter_tasks(
+        Tasks::find().filter(tasks::Column::ProjectId.eq(Some(project_id))),
+        filter,
+    );
+
+    find_task_page(db, query, sort, offset, limit).await
+}
+
+/// Tasks that go with the accounts matching `users`: everything in their
+/// projects and their own tasks outside of any project. Tasks they added to
+/// projects shared with them stay with those projects.
+pub fn account_tasks(users: Condition) -> Condition {
+    let user_ids = || {
+        Users::find()
+            .select_only()
+            .column(users::Column::Id)
+            .filter(users.clone())
+            .into_query()
+    };
+    let project_ids = Projects::find()
+        .select_only()
+        .column(projects::Column::Id)
+        .filter(projects::Column::UserId.in_subquery(user_ids()))
+        .into_query();
+
+    Condition::any()
+        .add(tasks::Column::ProjectId.in_subquery(project_ids))
+        .add(
+            Condition::all()
+                .add(tasks::Column::ProjectId.is_null())
+                .add(tasks::Column::UserId.in_subquery(user_ids())),
+        )
+}
+
+/// Tasks the user can see: those in projects they own or are a member of,
+/// and their own tasks that predate projects.
+pub fn visible_to(user_id: i32) -> Condition {
+    Condition::any()
+        .add(tasks::Column::ProjectId.in_subquery(owned_project_ids(user_id)))
+        .add(tasks::Column::ProjectId.in_subquery(member_project_ids(user_id)))
+        .add(
+            Condition::all()
+                .add(tasks::Column::ProjectId.is_null())
+                .add(tasks::Column::UserId.eq(Some(user_id))),
+        )
+}
+
+/// Moves every task of the user to the trash, e.g. when their account is
+/// deleted. Tagging them with the time the account was deleted lets
+/// [`restore_user_tasks`] bring back exactly these.
+pub async fn soft_delete_user_tasks(
+    db: &DatabaseConnection, user_id: i32, deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    Tasks::update_many()
+        .col_expr(tasks::Column::DeletedAt, Expr::value(Some(deleted_at)))
+        .filter(account_tasks(
+            Condition::all().add(users::Column::Id.eq(user_id)),
+        ))
+        .filter(tasks::Column::DeletedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error deleting user tasks: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error deleting tasks",
+            )
+        })?;
+
+    let trashed = Tasks::find()
+        .select_only()
+        .column(tasks::Column::Id)
+        .filter(account_tasks(
+            Condition::all().add(users::Column::Id.eq(user_id)),
+        ))
+        .filter(tasks::Column::DeletedAt.eq(deleted_at))
+        .into_query();
+    soft_delete_items_of_tasks(db, trashed, deleted_at).await
+}
+
+/// Takes the tasks that went to the trash along with the account back out,
+/// with their items.
+pub async fn restore_user_tasks(
+    db: &DatabaseConnection, user_id: i32, deleted_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    let trashed = Tasks::find()
+        .select_only()
+        .column(tasks::Column::Id)
+        .filter(account_tasks(
+            Condition::all().add(users::Column::Id.eq(user_id)),
+        ))
+        .filter(tasks::Column::DeletedAt.gte(deleted_at))
+        .into_query();
+    restore_items_of_tasks(db, trashed, deleted_at).await?;
+
+    Tasks::update_many()
+        .col_expr(
+            tasks::Column::DeletedAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .filter(account_tasks(
+            Condition::all().add(users::Column::Id.eq(user_id)),
+        ))
+        .filter(tasks::Column::DeletedAt.gte(deleted_at))
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error restoring user tasks: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error restoring tasks",
+            )
+        })?;
+
+    Ok(())
+}
+
+pub async fn delete_task_permanently(
+    db: &DatabaseConnection, task: TaskModel,
+) -> Result<(), AppError> {
+    Tasks::delete_by_id(task.id)
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error deleting task: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error deleting task",
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Empties the trash of tasks deleted before `deleted_before`. Tasks of
+/// soft-deleted users are left for the user purge, so restoring an account
+/// still brings them back.
+pub async fn purge_trashed_tasks(
+    db: &DatabaseConnection, deleted_before: DateTime<Utc>,
+) -> Result<u64, AppError> {
+    let deleted_users = || {
+        Users::find()
+            .select_only()
+            .column(users::Column::Id)
+            .filter(users::Column::DeletedAt.is_not_null())
+            .into_query()
+    };
+    let deleted_projects = Projects::find()
+        .select_only()
+        .column(projects::Column::Id)
+        .filter(projects::Column::UserId.in_subquery(deleted_users()))
+        .into_query();
+
+    let result = Tasks::delete_many()
+        .filter(tasks::Column::DeletedAt.lt(deleted_before))
+        .filter(
+            Condition::any()
+                .add(
+                    Condition::all()
+                        .add(tasks::Column::ProjectId.is_not_null())
+                        .add(
+                            tasks::Column::ProjectId
+                                .not_in_subquery(deleted_projects),
+                        ),
+                )
+                .add(
+                    Condition::all()
+                        .add(tasks::Column::ProjectId.is_null())
+                        .add(
+                            Condition::any()
+                                .add(tasks::Column::UserId.is_null())
+                                .add(
+                                    tasks::Column::UserId
+                                        .not_in_subquery(deleted_users()),
+                                ),
+                        ),
+                ),
+        )
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error purging trashed tasks: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error purging trashed tasks",
+            )
+        })?;
+
+    Ok(result.rows_affected)
+}
+
+/// Clears the assignee of the tasks in the project assigned to the user, e.g.
+/// when they leave it.
+pub async fn unassign_project_tasks(
+    db: &DatabaseConnection, project_id: i32, user_id: i32,
+) -> Result<(), AppError> {
+    Tasks::update_many()
+        .col_expr(tasks::Column::AssigneeId, Expr::value(Option::<i32>::None))
+        .filter(tasks::Column::ProjectId.eq(Some(project_id)))
+        .filter(tasks::Column::AssigneeId.eq(Some(user_id)))
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error unassigning project tasks: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Open tasks whose reminder is due and has not fired yet.
+pub async fn find_due_reminders(
+    db: &DatabaseConnection, now: DateTimeWithTimeZone,
+) -> Result<Vec<TaskModel>, AppError> {
+    Tasks::find()
+        .filter(tasks::Column::RemindAt.lte(now))
+        .filter(tasks::Column::RemindedAt.is_null())
+        .filter(tasks::Column::DeletedAt.is_null())
+        .filter(open_tasks())
+        .order_by_asc(tasks::Column::RemindAt)
+        .all(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting due reminders: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting due reminders",
+            )
+        })
+}
+
+/// Records that the task's reminder went out. Skipped when `remind_at` was
+/// changed in the meantime, so the new reminder still fires.
+pub async fn mark_task_reminded(
+    db: &DatabaseConnection, task: &TaskModel,
+    reminded_at: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    Tasks::update_many()
+        .col_expr(tasks::Column::RemindedAt, Expr::value(Some(reminded_at)))
+        .filter(tasks::Column::Id.eq(task.id))
+        .filter(tasks::Column::RemindAt.eq(task.remind_at))
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error marking task reminded: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error marking task reminded",
+            )
+        })?;
+
+    Ok(())
 }
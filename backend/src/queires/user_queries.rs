@@ -1,13 +1,46 @@
 use crate::{
-    database::users::{self, Entity as Users, Model as UserModel},
+    database::{
+        tasks::{self, Entity as Tasks},
+        users::{self, Entity as Users, Model as UserModel},
+    },
+    queires::task_queries::account_tasks,
     utils::app_error::AppError,
 };
 use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
-    QueryFilter, TryIntoModel,
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, Condition,
+    DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect,
+    QueryTrait, TryIntoModel,
 };
 
+/// Records the TOTP time step a code was accepted for. Fails when that step
+/// or a later one was already used, so two requests racing with the same
+/// code cannot both get through.
+pub async fn claim_totp_step(
+    db: &DatabaseConnection, user_id: i32, step: i64,
+) -> Result<bool, AppError> {
+    let result = Users::update_many()
+        .col_expr(users::Column::TotpLastStep, Expr::value(Some(step)))
+        .filter(users::Column::Id.eq(user_id))
+        .filter(
+            Condition::any()
+                .add(users::Column::TotpLastStep.is_null())
+                .add(users::Column::TotpLastStep.lt(step)),
+        )
+        .exec(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error recording totp step: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok(result.rows_affected == 1)
+}
+
 pub async fn save_active_user(
     db: &DatabaseConnection, user: users::ActiveModel,
 ) -> Result<UserModel, AppError> {
@@ -38,6 +71,7 @@ pub async fn find_by_username(
 ) -> Result<UserModel, AppError> {
     Users::find()
         .filter(users::Column::Username.eq(username))
+        .filter(users::Column::DeletedAt.is_null())
         .one(db)
         .await
         .map_err(|error| {
@@ -55,6 +89,123 @@ pub async fn find_by_username(
         })
 }
 
+/// Like [`find_by_username`], but for callers that need to tell a missing
+/// user apart from a failed lookup.
+pub async fn try_find_by_username(
+    db: &DatabaseConnection, username: &str,
+) -> Result<Option<UserModel>, AppError> {
+    Users::find()
+        .filter(users::Column::Username.eq(username))
+        .filter(users::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting user by username: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })
+}
+
+/// Finds an active user, soft-deleted accounts are treated as missing.
+pub async fn find_by_id(
+    db: &DatabaseConnection, id: i32,
+) -> Result<UserModel, AppError> {
+    Users::find_by_id(id)
+        .filter(users::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting user by id: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))
+}
+
+pub async fn find_users_by_ids(
+    db: &DatabaseConnection, ids: Vec<i32>,
+) -> Result<Vec<UserModel>, AppError> {
+    Users::find()
+        .filter(users::Column::Id.is_in(ids))
+        .filter(users::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting users by id: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })
+}
+
+/// Finds a soft-deleted user, e.g. to restore the account.
+pub async fn find_deleted_by_id(
+    db: &DatabaseConnection, id: i32,
+) -> Result<UserModel, AppError> {
+    Users::find_by_id(id)
+        .filter(users::Column::DeletedAt.is_not_null())
+        .one(db)
+        .await
+        .map_err(|error| {
+            eprintln!("Error getting deleted user by id: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))
+}
+
+/// Hard-deletes the users that were soft-deleted before `deleted_before`,
+/// along with their tasks. Everything else they own, projects included,
+/// goes with the `ON DELETE CASCADE` of its foreign key.
+pub async fn purge_deleted_users(
+    db: &DatabaseConnection, deleted_before: DateTime<Utc>,
+) -> Result<u64, AppError> {
+    let purge_error = |error: DbErr| {
+        eprintln!("Error purging deleted users: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error purging deleted users",
+        )
+    };
+
+    let expired =
+        Condition::all().add(users::Column::DeletedAt.lt(deleted_before));
+
+    Tasks::delete_many()
+        .filter(account_tasks(expired.clone()))
+        .exec(db)
+        .await
+        .map_err(purge_error)?;
+
+    // What they added to projects shared with them stays there.
+    let expired_ids = Users::find()
+        .select_only()
+        .column(users::Column::Id)
+        .filter(expired)
+        .into_query();
+    Tasks::update_many()
+        .col_expr(tasks::Column::UserId, Expr::value(Option::<i32>::None))
+        .filter(tasks::Column::UserId.in_subquery(expired_ids))
+        .exec(db)
+        .await
+        .map_err(purge_error)?;
+
+    let result = Users::delete_many()
+        .filter(users::Column::DeletedAt.lt(deleted_before))
+        .exec(db)
+        .await
+        .map_err(purge_error)?;
+
+    Ok(result.rows_affected)
+}
+
 fn convert_active_to_model(
     active_user: users::ActiveModel,
 ) -> Result<UserModel, AppError> {
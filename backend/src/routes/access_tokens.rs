@@ -0,0 +1,131 @@
+use crate::{
+    database::{
+        access_tokens::Model as AccessTokenModel, users::Model as UserModel,
+    },
+    queires::access_token_queries::{
+        create_access_token, delete_access_token, find_access_tokens_by_user,
+    },
+    utils::{app_error::AppError, scope::Scope},
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::owned_resource::Owned;
+
+const DEFAULT_EXPIRY_DAYS: i64 = 90;
+
+#[derive(Deserialize, Validate)]
+pub struct RequestAccessToken {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "must have 1 to 64 characters"
+    ))]
+    pub name: String,
+    #[validate(length(min = 1, message = "must have at least one scope"))]
+    pub scopes: Vec<Scope>,
+    #[validate(range(
+        min = 1,
+        max = 365,
+        message = "must be between 1 and 365"
+    ))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ResponseAccessToken {
+    id: i32,
+    name: String,
+    scopes: Vec<Scope>,
+    expires_at: DateTime<FixedOffset>,
+    last_used_at: Option<DateTime<FixedOffset>>,
+    created_at: DateTime<FixedOffset>,
+    /// Only set in the response to creating the token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ResponseDataAccessTokens {
+    pub data: Vec<ResponseAccessToken>,
+}
+
+impl From<AccessTokenModel> for ResponseAccessToken {
+    fn from(access_token: AccessTokenModel) -> Self {
+        Self {
+            id: access_token.id,
+            name: access_token.name,
+            scopes: Scope::parse_list(&access_token.scopes),
+            expires_at: access_token.expires_at,
+            last_used_at: access_token.last_used_at,
+            created_at: access_token.created_at,
+            token: None,
+        }
+    }
+}
+
+pub async fn create_my_token(
+    State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>,
+    Json(request): Json<RequestAccessToken>,
+) -> Result<(StatusCode, Json<ResponseAccessToken>), AppError> {
+    if let Err(errors) = request.validate() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, errors.to_string()));
+    }
+
+    let days = request.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+    let expires_at = Utc::now()
+        + Duration::try_days(days).expect("Failed to create duration");
+
+    let (access_token, token) = create_access_token(
+        &db,
+        user.id,
+        request.name,
+        &request.scopes,
+        expires_at,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ResponseAccessToken {
+            token: Some(token),
+            ..access_token.into()
+        }),
+    ))
+}
+
+pub async fn get_my_tokens(
+    State(db): State<DatabaseConnection>, Extension(user): Extension<UserModel>,
+) -> Result<(StatusCode, Json<ResponseDataAccessTokens>), AppError> {
+    let access_tokens = find_access_tokens_by_user(&db, user.id)
+        .await?
+        .into_iter()
+        .map(ResponseAccessToken::from)
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(ResponseDataAccessTokens {
+            data: access_tokens,
+        }),
+    ))
+}
+
+pub async fn get_my_token(
+    Owned(access_token): Owned<AccessTokenModel>,
+) -> Json<ResponseAccessToken> {
+    Json(access_token.into())
+}
+
+pub async fn delete_my_token(
+    Owned(access_token): Owned<AccessTokenModel>,
+    State(db): State<DatabaseConnection>,
+) -> Result<StatusCode, AppError> {
+    delete_access_token(&db, access_token).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,64 @@
+use super::{
+    get_tasks::{task_response, ResponseTask},
+    project_access::{Editor, ProjectAccess},
+};
+use crate::{
+    database::tasks::Model as TaskModel,
+    queires::{
+        project_member_queries::find_project_role,
+        project_queries::find_project_by_id, task_queries::save_active_task,
+        user_queries::find_by_id,
+    },
+    utils::{app_error::AppError, project_role::ProjectRole},
+};
+use axum::{extract::State, http::StatusCode, Json};
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct RequestAssignTask {
+    /// `null` unassigns the task.
+    pub assignee_id: Option<i32>,
+}
+
+/// Whether the user may be assigned a task in the project: they have to be
+/// able to edit it. Tasks outside of any project can only go to their
+/// creator.
+pub async fn can_be_assigned(
+    db: &DatabaseConnection, task: &TaskModel, project_id: Option<i32>,
+    assignee_id: i32,
+) -> Result<bool, AppError> {
+    let Some(project_id) = project_id else {
+        return Ok(task.user_id == Some(assignee_id));
+    };
+
+    let project = find_project_by_id(db, project_id).await?;
+    let role = find_project_role(db, &project, assignee_id).await?;
+
+    Ok(role.is_some_and(|role| role >= ProjectRole::Editor))
+}
+
+pub async fn assign_task(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess { resource: task, .. }: ProjectAccess<TaskModel, Editor>,
+    Json(request): Json<RequestAssignTask>,
+) -> Result<Json<ResponseTask>, AppError> {
+    if let Some(assignee_id) = request.assignee_id {
+        let assignee = find_by_id(&db, assignee_id).await.map_err(|_| {
+            AppError::new(StatusCode::BAD_REQUEST, "assignee does not exist")
+        })?;
+
+        if !can_be_assigned(&db, &task, task.project_id, assignee.id).await? {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                "the assignee needs to be able to edit the task",
+            ));
+        }
+    }
+
+    let mut task = task.into_active_model();
+    task.assignee_id = Set(request.assignee_id);
+    let task = save_active_task(&db, task).await?;
+
+    Ok(Json(task_response(&db, task).await?))
+}
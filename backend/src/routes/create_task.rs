@@ -1,6 +1,9 @@
 use crate::{
-    database::users::Model as UserModel, queires::task_queries,
-    utils::app_error::AppError,
+    database::users::Model as UserModel,
+    queires::{project_queries::find_or_create_inbox, task_queries},
+    utils::{
+        app_error::AppError, project_role::ProjectRole, task_status::TaskStatus,
+    },
 };
 use axum::{
     async_trait,
@@ -9,10 +12,12 @@ use axum::{
     http::{Request, StatusCode},
     BoxError, Extension, Json, RequestExt,
 };
-use sea_orm::DatabaseConnection;
+use sea_orm::{prelude::DateTimeWithTimeZone, DatabaseConnection};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+use super::project_access::{require_project_role, require_unarchived};
+
 #[derive(Debug, Validate, Deserialize)]
 pub struct ValidateCreateTask {
     #[validate(length(min = 1, max = 1))]
@@ -20,6 +25,10 @@ pub struct ValidateCreateTask {
     #[validate(required(message = "missing task title"))]
     pub title: Option<String>,
     pub description: Option<String>,
+    /// Defaults to the user's inbox.
+    pub project_id: Option<i32>,
+    pub due_at: Option<DateTimeWithTimeZone>,
+    pub remind_at: Option<DateTimeWithTimeZone>,
 }
 
 #[async_trait]
@@ -48,7 +57,7 @@ where
 
         if let Err(errors) = task.validate() {
             let field_errors = errors.field_errors();
-            for (_, error) in field_errors {
+            if let Some((_, error)) = field_errors.into_iter().next() {
                 return Err(AppError::new(
                     StatusCode::BAD_REQUEST,
                     error.first().unwrap().clone().message.unwrap().to_string(), // feel safe unwrapping because we know there is at least one error, and we only care about the first for this api
@@ -67,23 +76,40 @@ pub struct ResponseTask {
     pub description: Option<String>,
     pub priority: Option<String>,
     pub completed_at: Option<String>,
+    pub status: TaskStatus,
     pub user_id: Option<i32>,
+    pub project_id: Option<i32>,
+    pub due_at: Option<DateTimeWithTimeZone>,
+    pub remind_at: Option<DateTimeWithTimeZone>,
 }
 
 pub async fn create_task(
     Extension(user): Extension<UserModel>,
     State(db): State<DatabaseConnection>, task: ValidateCreateTask,
 ) -> Result<(StatusCode, Json<ResponseTask>), AppError> {
-    let task = task_queries::create_task(task, &user, &db).await?;
+    let project = match task.project_id {
+        Some(project_id) => {
+            require_project_role(&db, &user, project_id, ProjectRole::Editor)
+                .await?
+        }
+        None => find_or_create_inbox(&db, user.id).await?,
+    };
+    require_unarchived(&project)?;
+
+    let task = task_queries::create_task(task, &user, project.id, &db).await?;
 
     Ok((
         StatusCode::CREATED,
         Json(ResponseTask {
+            status: TaskStatus::of(&task),
             id: task.id,
             title: task.title,
             description: task.description,
             priority: task.priority,
             user_id: task.user_id,
+            project_id: task.project_id,
+            due_at: task.due_at,
+            remind_at: task.remind_at,
             completed_at: task.completed_at.map(|time| time.to_string()),
         }),
     ))
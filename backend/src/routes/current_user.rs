@@ -0,0 +1,140 @@
+use crate::{
+    app_state::AppConfig,
+    database::users::Model as UserModel,
+    queires::{
+        access_token_queries::delete_user_access_tokens,
+        refresh_token_queries::{
+            revoke_other_refresh_tokens, revoke_user_refresh_tokens,
+        },
+        session_queries::{
+            find_session_by_jti, revoke_all_sessions, revoke_other_sessions,
+        },
+        task_queries::soft_delete_user_tasks,
+        user_queries::save_active_user,
+    },
+    utils::{
+        app_error::AppError, auth_cookies::AuthCookies, jwt::Claims,
+        mailer::SharedMailer, password_hasher::PasswordHasher,
+    },
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::Deserialize;
+use validator::Validate;
+
+use super::{users::ResponseUser, verify_email::send_verification_email};
+
+#[derive(Deserialize, Validate)]
+pub struct RequestUpdateMe {
+    #[validate(email(message = "must be a valid email"))]
+    pub username: Option<String>,
+    #[validate(length(min = 8, message = "must have at least 8 characters"))]
+    pub password: Option<String>,
+    /// Required to change the email or the password.
+    pub current_password: Option<String>,
+}
+
+pub async fn get_me(
+    Extension(user): Extension<UserModel>,
+) -> Json<ResponseUser> {
+    Json(user.into())
+}
+
+pub async fn update_me(
+    Extension(user): Extension<UserModel>,
+    Extension(claims): Extension<Claims>, State(db): State<DatabaseConnection>,
+    State(hasher): State<PasswordHasher>, State(mailer): State<SharedMailer>,
+    State(config): State<AppConfig>, Json(request): Json<RequestUpdateMe>,
+) -> Result<Json<ResponseUser>, AppError> {
+    if let Err(errors) = request.validate() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, errors.to_string()));
+    }
+
+    let username = request
+        .username
+        .filter(|username| *username != user.username);
+
+    if username.is_none() && request.password.is_none() {
+        return Ok(Json(user.into()));
+    }
+
+    // Someone walking up to an unlocked session must not be able to take
+    // the account over.
+    let Some(current_password) = request.current_password else {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "current_password is required to change the email or password",
+        ));
+    };
+    if !hasher
+        .verify(current_password, user.password.clone())
+        .await?
+    {
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "current password is incorrect",
+        ));
+    }
+
+    let password_changed = request.password.is_some();
+    let mut active_user = user.into_active_model();
+
+    if let Some(username) = &username {
+        active_user.username = Set(username.clone());
+        active_user.verified_at = Set(None);
+    }
+
+    if let Some(password) = request.password {
+        active_user.password = Set(hasher.hash(password).await?);
+    }
+
+    let user = save_active_user(&db, active_user).await?;
+
+    // Only the device that made the change stays signed in, and tokens
+    // handed out with the old password stop working.
+    if password_changed {
+        let session = find_session_by_jti(&db, &claims.jti).await?;
+        revoke_other_refresh_tokens(
+            &db,
+            user.id,
+            session.map(|session| session.id),
+        )
+        .await?;
+        revoke_other_sessions(&db, user.id, &claims.jti).await?;
+        delete_user_access_tokens(&db, user.id).await?;
+    }
+
+    // The new address has to be confirmed like the first one was.
+    if username.is_some()
+        && send_verification_email(&db, &mailer, &config, &user)
+            .await
+            .is_err()
+    {
+        eprintln!("Error sending verification to changed user {}", user.id);
+    }
+
+    Ok(Json(user.into()))
+}
+
+/// Soft-deletes the account along with its tasks and signs it out
+/// everywhere.
+pub async fn delete_me(
+    cookies: AuthCookies, Extension(user): Extension<UserModel>,
+    State(db): State<DatabaseConnection>,
+) -> Result<StatusCode, AppError> {
+    let user_id = user.id;
+    let deleted_at = Utc::now().into();
+    let mut active_user = user.into_active_model();
+    active_user.deleted_at = Set(Some(deleted_at));
+    save_active_user(&db, active_user).await?;
+
+    soft_delete_user_tasks(&db, user_id, deleted_at).await?;
+    revoke_all_sessions(&db, user_id).await?;
+    revoke_user_refresh_tokens(&db, user_id).await?;
+    delete_user_access_tokens(&db, user_id).await?;
+
+    cookies.end_session();
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,28 +1,91 @@
+use super::{
+    get_tasks::{task_response, ResponseTask},
+    project_access::{AnyTask, Editor, ProjectAccess},
+    require_role::{Admin, RequireRole},
+};
 use crate::{
-    database::users::Model,
-    queires::task_queries::{find_task_by_id, save_active_task},
-    utils::app_error::AppError,
+    queires::{
+        task_item_queries::{restore_task_items, soft_delete_task_items},
+        task_queries::{delete_task_permanently, save_active_task},
+    },
+    utils::{app_error::AppError, project_role::ProjectRole},
 };
 use axum::{
-    extract::{Path, State},
-    Extension,
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
 };
 use chrono::Utc;
 use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct DeleteTaskQuery {
+    /// Skips the trash, the task cannot be restored afterwards.
+    #[serde(default)]
+    pub permanent: bool,
+}
 
+/// Moves the task to the trash, or with `permanent` deletes it for good,
+/// which only the project owner or an admin can do, trashed or not.
 pub async fn delete_task(
-    State(db): State<DatabaseConnection>, Path(task_id): Path<i32>,
-    Extension(user): Extension<Model>,
+    State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: AnyTask(task),
+        role,
+        ..
+    }: ProjectAccess<AnyTask, Editor>,
+    Query(query): Query<DeleteTaskQuery>,
 ) -> Result<(), AppError> {
-    let mut task = find_task_by_id(&db, task_id, user.id)
-        .await?
-        .into_active_model();
+    if query.permanent {
+        if role < ProjectRole::Owner {
+            return Err(AppError::new(
+                StatusCode::FORBIDDEN,
+                "only the project owner can delete a task permanently",
+            ));
+        }
+
+        return delete_task_permanently(&db, task).await;
+    }
+
+    if task.deleted_at.is_some() {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "not found"));
+    }
+
+    let task_id = task.id;
+    let mut task = task.into_active_model();
 
-    let now = Utc::now();
+    let now = Utc::now().into();
 
-    task.deleted_at = Set(Some(now.into()));
+    task.deleted_at = Set(Some(now));
 
     save_active_task(&db, task).await?;
+    soft_delete_task_items(&db, task_id, now).await?;
 
     Ok(())
 }
+
+/// Takes a task back out of the trash. Reserved for admins, like clearing
+/// `deleted_at` through PATCH or PUT.
+pub async fn restore_task(
+    _admin: RequireRole<Admin>, State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: AnyTask(task),
+        ..
+    }: ProjectAccess<AnyTask, Editor>,
+) -> Result<Json<ResponseTask>, AppError> {
+    let Some(deleted_at) = task.deleted_at else {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "task is not in the trash",
+        ));
+    };
+
+    let mut task = task.into_active_model();
+    task.deleted_at = Set(None);
+
+    let task = save_active_task(&db, task).await?;
+    restore_task_items(&db, task.id, deleted_at).await?;
+
+    Ok(Json(task_response(&db, task).await?))
+}
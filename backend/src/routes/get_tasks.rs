@@ -1,16 +1,24 @@
 use crate::{
-    database::users::Model,
-    queires::task_queries::{find_all_tasks, find_task_by_id},
-    utils::app_error::AppError,
+    database::{tasks::Model as TaskModel, users::Model},
+    queires::task_item_queries::{find_item_progress, ItemProgress},
+    queires::task_queries::{
+        find_task_by_id, find_tasks, TaskAssignee, TaskFilter, TaskPage,
+        TaskSort, TaskSortField,
+    },
+    utils::{app_error::AppError, task_status::TaskStatus},
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Extension, Json,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{DateTime, FixedOffset};
 use sea_orm::DatabaseConnection;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 100;
 
 #[derive(Serialize)]
 pub struct ResponseTask {
@@ -19,13 +27,210 @@ pub struct ResponseTask {
     priority: Option<String>,
     description: Option<String>,
     completed_at: Option<String>,
+    status: TaskStatus,
     user_id: Option<i32>,
+    project_id: Option<i32>,
+    assignee_id: Option<i32>,
+    due_at: Option<DateTime<FixedOffset>>,
+    remind_at: Option<DateTime<FixedOffset>>,
     deleted_at: Option<DateTime<FixedOffset>>,
+    /// Roll-up of the task's checklist items.
+    items: ItemProgress,
+}
+
+impl From<TaskModel> for ResponseTask {
+    fn from(task: TaskModel) -> Self {
+        Self {
+            status: TaskStatus::of(&task),
+            id: task.id,
+            title: task.title,
+            description: task.description,
+            priority: task.priority,
+            completed_at: task.completed_at.map(|time| time.to_string()),
+            user_id: task.user_id,
+            project_id: task.project_id,
+            assignee_id: task.assignee_id,
+            due_at: task.due_at,
+            remind_at: task.remind_at,
+            deleted_at: task.deleted_at,
+            items: ItemProgress::default(),
+        }
+    }
+}
+
+/// Builds the responses for the tasks along with their item roll-up.
+pub async fn task_responses(
+    db: &DatabaseConnection, tasks: Vec<TaskModel>,
+) -> Result<Vec<ResponseTask>, AppError> {
+    let task_ids = tasks.iter().map(|task| task.id).collect::<Vec<_>>();
+    let progress = find_item_progress(db, &task_ids).await?;
+
+    Ok(tasks
+        .into_iter()
+        .map(|task| {
+            let items = progress.get(&task.id).copied().unwrap_or_default();
+            ResponseTask {
+                items,
+                ..task.into()
+            }
+        })
+        .collect())
+}
+
+pub async fn task_response(
+    db: &DatabaseConnection, task: TaskModel,
+) -> Result<ResponseTask, AppError> {
+    let mut responses = task_responses(db, vec![task]).await?;
+
+    Ok(responses.remove(0))
 }
 
 #[derive(Serialize)]
 pub struct ResponseDataTasks {
     pub data: Vec<ResponseTask>,
+    /// How many tasks match the filter across all pages.
+    pub total: u64,
+    /// Pass as `cursor` to get the next page, missing on the last one.
+    pub next_cursor: Option<String>,
+}
+
+/// Filtering, sorting and pagination shared by the task list views.
+#[derive(Deserialize)]
+pub struct GetTasksQuery {
+    pub priority: Option<String>,
+    pub status: Option<TaskStatus>,
+    /// `me`, `none` or a user id.
+    pub assignee: Option<String>,
+    pub completed: Option<bool>,
+    pub search: Option<String>,
+    pub completed_after: Option<DateTime<FixedOffset>>,
+    pub completed_before: Option<DateTime<FixedOffset>>,
+    pub overdue: Option<bool>,
+    pub due_before: Option<DateTime<FixedOffset>>,
+    /// Comma separated fields, descending when prefixed with `-`, e.g.
+    /// `-priority,title`.
+    pub sort: Option<String>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    pub cursor: Option<String>,
+}
+
+fn invalid_cursor() -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, "invalid cursor")
+}
+
+/// Cursors are opaque to clients so the paging strategy can change without
+/// breaking them.
+fn encode_cursor(offset: u64) -> String {
+    URL_SAFE_NO_PAD.encode(offset.to_string())
+}
+
+fn decode_cursor(cursor: &str) -> Result<u64, AppError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|_| invalid_cursor())?;
+
+    String::from_utf8(bytes)
+        .ok()
+        .and_then(|offset| offset.parse().ok())
+        .ok_or_else(invalid_cursor)
+}
+
+impl GetTasksQuery {
+    pub fn filter(&self, user: &Model) -> Result<TaskFilter, AppError> {
+        let assignee = match self.assignee.as_deref() {
+            None => None,
+            Some("me") => Some(TaskAssignee::User(user.id)),
+            Some("none") => Some(TaskAssignee::Unassigned),
+            Some(assignee) => {
+                let user_id = assignee.parse().map_err(|_| {
+                    AppError::new(
+                        StatusCode::BAD_REQUEST,
+                        "assignee must be me, none or a user id",
+                    )
+                })?;
+                Some(TaskAssignee::User(user_id))
+            }
+        };
+
+        Ok(TaskFilter {
+            priority: self.priority.clone(),
+            status: self.status,
+            assignee,
+            completed: self.completed,
+            search: self.search.clone(),
+            completed_after: self.completed_after,
+            completed_before: self.completed_before,
+            overdue: self.overdue,
+            due_before: self.due_before,
+            deleted: false,
+        })
+    }
+
+    pub fn sort(&self) -> Result<Vec<TaskSort>, AppError> {
+        let Some(sort) = &self.sort else {
+            return Ok(vec![]);
+        };
+
+        sort.split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                let (descending, name) = match field.strip_prefix('-') {
+                    Some(name) => (true, name),
+                    None => (false, field),
+                };
+
+                TaskSortField::parse(name)
+                    .map(|field| TaskSort { field, descending })
+                    .ok_or_else(|| {
+                        AppError::new(
+                            StatusCode::BAD_REQUEST,
+                            format!("cannot sort by {name:?}"),
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// The offset and page size to fetch, a cursor taking precedence over
+    /// an explicit offset.
+    pub fn page(&self) -> Result<(u64, u64), AppError> {
+        let offset = match &self.cursor {
+            Some(cursor) => decode_cursor(cursor)?,
+            None => self.offset.unwrap_or(0),
+        };
+        // The database takes signed offsets.
+        if offset > i64::MAX as u64 {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                "offset is too large",
+            ));
+        }
+        let limit = self
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        Ok((offset, limit))
+    }
+}
+
+impl ResponseDataTasks {
+    pub async fn from_page(
+        db: &DatabaseConnection, page: TaskPage, offset: u64,
+    ) -> Result<Self, AppError> {
+        let next_cursor = offset
+            .checked_add(page.tasks.len() as u64)
+            .filter(|next| !page.tasks.is_empty() && *next < page.total)
+            .map(encode_cursor);
+
+        Ok(Self {
+            data: task_responses(db, page.tasks).await?,
+            total: page.total,
+            next_cursor,
+        })
+    }
 }
 
 pub async fn get_one_task(
@@ -34,36 +239,112 @@ pub async fn get_one_task(
 ) -> Result<(StatusCode, Json<ResponseTask>), AppError> {
     let task = find_task_by_id(&db, task_id, user.id).await?;
 
+    Ok((StatusCode::OK, Json(task_response(&db, task).await?)))
+}
+
+pub async fn get_all_tasks(
+    Query(query): Query<GetTasksQuery>, State(db): State<DatabaseConnection>,
+    Extension(user): Extension<Model>,
+) -> Result<(StatusCode, Json<ResponseDataTasks>), AppError> {
+    let sort = query.sort()?;
+    let (offset, limit) = query.page()?;
+    let filter = query.filter(&user)?;
+    let page = find_tasks(&db, user.id, &filter, &sort, offset, limit).await?;
+
     Ok((
         StatusCode::OK,
-        Json(ResponseTask {
-            id: task.id,
-            title: task.title,
-            description: task.description,
-            priority: task.priority,
-            completed_at: task.completed_at.map(|time| time.to_string()),
-            user_id: task.user_id,
-            deleted_at: task.deleted_at,
-        }),
+        Json(ResponseDataTasks::from_page(&db, page, offset).await?),
     ))
 }
 
-pub async fn get_all_tasks(
-    State(db): State<DatabaseConnection>, Extension(user): Extension<Model>,
+/// Lists the deleted tasks that have not been purged yet, most recently
+/// deleted first unless sorted otherwise.
+pub async fn get_trash(
+    Query(query): Query<GetTasksQuery>, State(db): State<DatabaseConnection>,
+    Extension(user): Extension<Model>,
 ) -> Result<(StatusCode, Json<ResponseDataTasks>), AppError> {
-    let tasks = find_all_tasks(&db, user.id, false)
-        .await?
-        .into_iter()
-        .map(|db_task| ResponseTask {
-            id: db_task.id,
-            title: db_task.title,
-            description: db_task.description,
-            priority: db_task.priority,
-            completed_at: db_task.completed_at.map(|time| time.to_string()),
-            user_id: db_task.user_id,
-            deleted_at: db_task.deleted_at,
-        })
-        .collect::<Vec<ResponseTask>>();
+    let mut sort = query.sort()?;
+    if sort.is_empty() {
+        sort.push(TaskSort {
+            field: TaskSortField::DeletedAt,
+            descending: true,
+        });
+    }
+
+    let (offset, limit) = query.page()?;
+    let filter = TaskFilter {
+        deleted: true,
+        ..query.filter(&user)?
+    };
+    let page = find_tasks(&db, user.id, &filter, &sort, offset, limit).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ResponseDataTasks::from_page(&db, page, offset).await?),
+    ))
+}
+
+/// The tasks assigned to the user across every project they can see.
+pub async fn get_assigned_to_me(
+    Query(query): Query<GetTasksQuery>, State(db): State<DatabaseConnection>,
+    Extension(user): Extension<Model>,
+) -> Result<(StatusCode, Json<ResponseDataTasks>), AppError> {
+    let sort = query.sort()?;
+    let (offset, limit) = query.page()?;
+    let filter = TaskFilter {
+        assignee: Some(TaskAssignee::User(user.id)),
+        ..query.filter(&user)?
+    };
+    let page = find_tasks(&db, user.id, &filter, &sort, offset, limit).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ResponseDataTasks::from_page(&db, page, offset).await?),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn cursors_round_trip() {
+        for offset in [0, 20, u64::MAX] {
+            assert_eq!(decode_cursor(&encode_cursor(offset)).unwrap(), offset);
+        }
+    }
+
+    #[test]
+    fn made_up_cursors_are_rejected() {
+        for cursor in ["", "%%%", "LTE", &URL_SAFE_NO_PAD.encode("twenty")] {
+            assert_eq!(
+                decode_cursor(cursor).unwrap_err().code(),
+                StatusCode::BAD_REQUEST
+            );
+        }
+    }
+
+    fn page(query: serde_json::Value) -> Result<(u64, u64), AppError> {
+        serde_json::from_value::<GetTasksQuery>(query)
+            .unwrap()
+            .page()
+    }
+
+    #[test]
+    fn offsets_past_what_the_database_takes_are_rejected() {
+        let max = i64::MAX as u64;
+        assert_eq!(page(json!({ "offset": max })).unwrap().0, max);
 
-    Ok((StatusCode::OK, Json(ResponseDataTasks { data: tasks })))
+        for query in [
+            json!({ "offset": max + 1 }),
+            json!({ "cursor": encode_cursor(u64::MAX) }),
+        ] {
+            assert_eq!(
+                page(query).unwrap_err().code(),
+                StatusCode::BAD_REQUEST
+            );
+        }
+    }
 }
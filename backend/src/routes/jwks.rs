@@ -0,0 +1,10 @@
+use axum::{extract::State, Json};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::utils::jwt::JwtIssuer;
+
+/// Publishes the public signing keys so other services can verify our
+/// tokens on their own.
+pub async fn jwks(State(jwt): State<JwtIssuer>) -> Json<JwkSet> {
+    Json(jwt.keys.jwks())
+}
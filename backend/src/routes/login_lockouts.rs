@@ -0,0 +1,111 @@
+use crate::utils::{
+    app_error::AppError,
+    client_info::ClientInfo,
+    login_throttle::{
+        account_key, ip_key, SharedLoginThrottle, ThrottlePolicy,
+        ACCOUNT_POLICY, IP_POLICY,
+    },
+};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+
+use super::require_role::{Admin, RequireRole};
+
+#[derive(Deserialize)]
+pub struct RequestUnlock {
+    pub username: Option<String>,
+    pub ip: Option<String>,
+}
+
+/// The throttle keys a login attempt counts against.
+pub fn login_keys(
+    username: &str, client: &ClientInfo,
+) -> Vec<(String, &'static ThrottlePolicy)> {
+    let mut keys = vec![(account_key(username), &ACCOUNT_POLICY)];
+
+    if let Some(ip) = &client.ip {
+        keys.push((ip_key(ip), &IP_POLICY));
+    }
+
+    keys
+}
+
+fn throttle_error(error: eyre::Report) -> AppError {
+    eprintln!("Error accessing login throttle: {:?}", error);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Error logging in, please try again later",
+    )
+}
+
+/// Fails with 429 and `Retry-After` while any of the keys is locked out.
+pub async fn ensure_not_locked(
+    throttle: &SharedLoginThrottle, keys: &[(String, &ThrottlePolicy)],
+) -> Result<(), AppError> {
+    let mut retry_after = None;
+
+    for (key, _) in keys {
+        let locked_for =
+            throttle.0.locked_for(key).await.map_err(throttle_error)?;
+        retry_after = retry_after.max(locked_for);
+    }
+
+    match retry_after {
+        Some(seconds) => Err(AppError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "too many failed login attempts, please try again later",
+        )
+        .with_retry_after(seconds)),
+        None => Ok(()),
+    }
+}
+
+pub async fn record_failed_login(
+    throttle: &SharedLoginThrottle, keys: &[(String, &ThrottlePolicy)],
+) -> Result<(), AppError> {
+    for (key, policy) in keys {
+        throttle
+            .0
+            .record_failure(key, policy)
+            .await
+            .map_err(throttle_error)?;
+    }
+
+    Ok(())
+}
+
+pub async fn clear_account_lockout(
+    throttle: &SharedLoginThrottle, username: &str,
+) -> Result<(), AppError> {
+    throttle
+        .0
+        .clear(&account_key(username))
+        .await
+        .map_err(throttle_error)
+}
+
+pub async fn unlock_login(
+    _admin: RequireRole<Admin>, State(throttle): State<SharedLoginThrottle>,
+    Json(request): Json<RequestUnlock>,
+) -> Result<StatusCode, AppError> {
+    if request.username.is_none() && request.ip.is_none() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "username or ip is required",
+        ));
+    }
+
+    if let Some(username) = request.username {
+        clear_account_lockout(&throttle, &username).await?;
+    }
+
+    if let Some(ip) = request.ip {
+        throttle
+            .0
+            .clear(&ip_key(&ip))
+            .await
+            .map_err(throttle_error)?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
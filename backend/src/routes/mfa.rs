@@ -0,0 +1,187 @@
+use crate::{
+    database::users::Model as UserModel,
+    queires::{
+        one_time_token_queries::{
+            consume_one_time_token, find_one_time_token, TokenPurpose,
+        },
+        recovery_code_queries::{
+            delete_recovery_codes, replace_recovery_codes, use_recovery_code,
+        },
+        user_queries::{claim_totp_step, find_by_id, save_active_user},
+    },
+    utils::{
+        app_error::AppError,
+        auth_cookies::AuthCookies,
+        client_info::ClientInfo,
+        jwt::JwtIssuer,
+        login_throttle::SharedLoginThrottle,
+        totp::{check_totp_code, generate_totp_secret, totp_url},
+    },
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    login_lockouts::{ensure_not_locked, login_keys, record_failed_login},
+    users::{complete_login, ResponseUser},
+};
+
+#[derive(Deserialize)]
+pub struct RequestMfaCode {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct RequestMfaLogin {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct ResponseTotpEnrollment {
+    secret: String,
+    otpauth_url: String,
+}
+
+#[derive(Serialize)]
+pub struct ResponseRecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+fn already_enabled() -> AppError {
+    AppError::new(
+        StatusCode::CONFLICT,
+        "two-factor authentication is already enabled",
+    )
+}
+
+/// Accepts a TOTP code that has not been used yet, each one only works once.
+async fn use_totp_code(
+    db: &DatabaseConnection, user: &UserModel, secret: &str, code: &str,
+) -> Result<bool, AppError> {
+    match check_totp_code(secret, &user.username, code, user.totp_last_step)? {
+        Some(step) => claim_totp_step(db, user.id, step).await,
+        None => Ok(false),
+    }
+}
+
+/// Accepts either a current TOTP code or one of the unused recovery codes.
+async fn check_second_factor(
+    db: &DatabaseConnection, user: &UserModel, code: &str,
+) -> Result<bool, AppError> {
+    if let Some(secret) = &user.totp_secret {
+        if use_totp_code(db, user, secret, code).await? {
+            return Ok(true);
+        }
+    }
+
+    use_recovery_code(db, user.id, code).await
+}
+
+/// Starts enrollment by generating a secret for the user's authenticator.
+/// Nothing changes at login until the secret is confirmed with a code.
+pub async fn enroll_totp(
+    Extension(user): Extension<UserModel>, State(db): State<DatabaseConnection>,
+) -> Result<Json<ResponseTotpEnrollment>, AppError> {
+    if user.totp_enabled_at.is_some() {
+        return Err(already_enabled());
+    }
+
+    let secret = generate_totp_secret();
+    let otpauth_url = totp_url(&secret, &user.username)?;
+
+    let mut user = user.into_active_model();
+    user.totp_secret = Set(Some(secret.clone()));
+    save_active_user(&db, user).await?;
+
+    Ok(Json(ResponseTotpEnrollment {
+        secret,
+        otpauth_url,
+    }))
+}
+
+pub async fn confirm_totp(
+    Extension(user): Extension<UserModel>,
+    State(db): State<DatabaseConnection>, Json(request): Json<RequestMfaCode>,
+) -> Result<Json<ResponseRecoveryCodes>, AppError> {
+    if user.totp_enabled_at.is_some() {
+        return Err(already_enabled());
+    }
+
+    let Some(secret) = &user.totp_secret else {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "two-factor authentication enrollment has not been started",
+        ));
+    };
+
+    if !use_totp_code(&db, &user, secret, &request.code).await? {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "invalid code"));
+    }
+
+    let user_id = user.id;
+    let mut user = user.into_active_model();
+    user.totp_enabled_at = Set(Some(Utc::now().into()));
+    save_active_user(&db, user).await?;
+
+    let recovery_codes = replace_recovery_codes(&db, user_id).await?;
+
+    Ok(Json(ResponseRecoveryCodes { recovery_codes }))
+}
+
+pub async fn disable_totp(
+    Extension(user): Extension<UserModel>,
+    State(db): State<DatabaseConnection>, Json(request): Json<RequestMfaCode>,
+) -> Result<StatusCode, AppError> {
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "two-factor authentication is not enabled",
+        ));
+    }
+
+    if !check_second_factor(&db, &user, &request.code).await? {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "invalid code"));
+    }
+
+    let user_id = user.id;
+    let mut user = user.into_active_model();
+    user.totp_secret = Set(None);
+    user.totp_enabled_at = Set(None);
+    save_active_user(&db, user).await?;
+
+    delete_recovery_codes(&db, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Second step of a login for users with two-factor authentication, trading
+/// the pending token from `/users/login` and a code for a session.
+pub async fn login_mfa(
+    cookies: AuthCookies, client: ClientInfo,
+    State(db): State<DatabaseConnection>, State(jwt): State<JwtIssuer>,
+    State(throttle): State<SharedLoginThrottle>,
+    Json(request): Json<RequestMfaLogin>,
+) -> Result<Json<ResponseUser>, AppError> {
+    let pending =
+        find_one_time_token(&db, &request.mfa_token, TokenPurpose::MfaPending)
+            .await?;
+    let user = find_by_id(&db, pending.user_id).await?;
+
+    // Codes count against the same lockout as passwords, so the pending
+    // token cannot be used to brute force the six digits.
+    let keys = login_keys(&user.username, &client);
+    ensure_not_locked(&throttle, &keys).await?;
+
+    if !check_second_factor(&db, &user, &request.code).await? {
+        record_failed_login(&throttle, &keys).await?;
+        return Err(AppError::new(StatusCode::UNAUTHORIZED, "invalid code"));
+    }
+
+    consume_one_time_token(&db, &request.mfa_token, TokenPurpose::MfaPending)
+        .await?;
+
+    complete_login(&cookies, &client, &db, &jwt, &throttle, user).await
+}
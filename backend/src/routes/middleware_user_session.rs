@@ -1,7 +1,17 @@
 use crate::{
-    database::users::{self, Entity as Users},
+    database::users::{self, Entity as Users, Model as UserModel},
+    queires::{
+        access_token_queries::{
+            find_active_access_token, touch_access_token, ACCESS_TOKEN_PREFIX,
+        },
+        revoked_token_queries::is_token_revoked,
+        session_queries::touch_session,
+    },
     utils::{
-        app_error::AppError, jwt::validate_token, token_wrapper::TokenWrapper,
+        app_error::AppError,
+        auth_cookies::AuthCookies,
+        jwt::{validate_token, JwtIssuer},
+        scope::Scope,
     },
 };
 use axum::{
@@ -14,33 +24,98 @@ use axum::{
 };
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 
-pub async fn user_session<T>(
-    TypedHeader(token): TypedHeader<Authorization<Bearer>>,
-    State(database): State<DatabaseConnection>,
-    State(jwt_secret): State<TokenWrapper>, mut request: Request<T>,
-    next: Next<T>,
-) -> Result<Response, AppError> {
-    let token = token.token().to_owned();
-    let user = Users::find()
-        .filter(users::Column::Token.eq(Some(token.clone())))
-        .one(&database)
+fn not_authorized() -> AppError {
+    AppError::new(
+        StatusCode::UNAUTHORIZED,
+        "You are not authorized, please login or create account",
+    )
+}
+
+async fn find_user(
+    database: &DatabaseConnection, user_id: i32,
+) -> Result<UserModel, AppError> {
+    // Tokens issued before the account was deleted stop working with it.
+    Users::find_by_id(user_id)
+        .filter(users::Column::DeletedAt.is_null())
+        .one(database)
         .await
         .map_err(|_error| {
             AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error",
             )
-        })?;
-    validate_token(&jwt_secret.0, &token)?; // Validating token after getting from the database to obsfucate that the token is wrong. Feel free to move up if you are not worried about that.
+        })?
+        .ok_or_else(not_authorized)
+}
 
-    let Some(user) = user else {
-        return Err(AppError::new(
-            StatusCode::UNAUTHORIZED,
-            "You are not authorized, please login or create account",
-        ));
+/// Authenticates the request from the `Authorization` header, or from the
+/// auth cookie the browser sends.
+pub async fn user_session<T>(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>, cookies: AuthCookies,
+    State(database): State<DatabaseConnection>, State(jwt): State<JwtIssuer>,
+    mut request: Request<T>, next: Next<T>,
+) -> Result<Response, AppError> {
+    let token = match bearer {
+        Some(TypedHeader(bearer)) => bearer.token().to_owned(),
+        None => {
+            let token = cookies.auth_token().ok_or_else(not_authorized)?;
+            // Browsers attach cookies to cross site requests by themselves,
+            // unlike headers.
+            cookies.verify_csrf(request.method(), request.headers())?;
+            token
+        }
     };
 
+    if token.starts_with(ACCESS_TOKEN_PREFIX) {
+        let user = access_token_user(&database, &token, &request).await?;
+        request.extensions_mut().insert(user);
+
+        return Ok(next.run(request).await);
+    }
+
+    // The signature is checked first so forged tokens never reach the
+    // database; only the denylist and the user row are looked up after.
+    let claims = validate_token(&jwt, &token)?;
+
+    if is_token_revoked(&database, &claims.jti).await? {
+        return Err(not_authorized());
+    }
+
+    let user = find_user(&database, claims.user_id()?).await?;
+    touch_session(&database, &claims.jti).await?;
+
     request.extensions_mut().insert(user);
+    request.extensions_mut().insert(claims);
 
     Ok(next.run(request).await)
 }
+
+/// Personal access tokens only get through to the routes their scopes
+/// cover.
+async fn access_token_user<T>(
+    database: &DatabaseConnection, token: &str, request: &Request<T>,
+) -> Result<UserModel, AppError> {
+    let access_token = find_active_access_token(database, token)
+        .await?
+        .ok_or_else(not_authorized)?;
+
+    let Some(required) =
+        Scope::required_for(request.method(), request.uri().path())
+    else {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "personal access tokens cannot be used here",
+        ));
+    };
+
+    if !Scope::parse_list(&access_token.scopes).contains(&required) {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            format!("token is missing the {} scope", required.as_str()),
+        ));
+    }
+
+    touch_access_token(database, access_token.id).await?;
+
+    find_user(database, access_token.user_id).await
+}
@@ -0,0 +1,22 @@
+use crate::{database::users::Model as UserModel, utils::app_error::AppError};
+use axum::{
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+
+/// Keeps users that have not confirmed their email out of the routes it
+/// wraps. Must run behind `user_session`.
+pub async fn verified_user<T>(
+    Extension(user): Extension<UserModel>, request: Request<T>, next: Next<T>,
+) -> Result<Response, AppError> {
+    if user.verified_at.is_none() {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Please verify your email address before continuing",
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
@@ -20,7 +20,7 @@ pub async fn mirror_body_json(
     Json(body): Json<MirrorJson>,
 ) -> Json<MirrorJsonResponse> {
     Json(MirrorJsonResponse {
-        id: 01,
+        id: 1,
         profile: body.profile,
         message: body.message,
         message_from_server: "Hello from Axum".to_owned(),
@@ -1,15 +1,35 @@
 // task routes
+mod assign_task;
 pub mod create_task;
 mod delete_task;
 mod get_tasks;
 mod hello_world;
 mod partial_update_task;
+mod project_access;
+mod project_members;
+mod projects;
+mod task_items;
+mod task_status;
 mod update_tasks;
 
 // users routes
+mod access_tokens;
+mod current_user;
+mod jwks;
+mod login_lockouts;
+mod mfa;
 mod middleware_user_session;
+mod middleware_verified_user;
+mod oidc_login;
+mod owned_resource;
 mod partial_update_user;
+mod password_reset;
+mod refresh_token;
+mod require_role;
+mod restore_user;
+mod sessions;
 mod users;
+mod verify_email;
 
 // essentials routes
 mod always_errors;
@@ -28,36 +48,65 @@ mod validate_json;
 
 use axum::{
     extract::FromRef,
-    http::Method,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        HeaderName, HeaderValue, Method,
+    },
     middleware,
     routing::{delete, get, patch, post, put},
     Extension, Router,
 };
 use tower_cookies::CookieManagerLayer;
 
-use crate::app_state::AppState;
+use crate::{app_state::AppState, utils::auth_cookies::CSRF_HEADER};
 
+use access_tokens::{
+    create_my_token, delete_my_token, get_my_token, get_my_tokens,
+};
 use always_errors::always_errors;
+use assign_task::assign_task;
 use create_task::create_task;
-use delete_task::delete_task;
+use current_user::{delete_me, get_me, update_me};
+use delete_task::{delete_task, restore_task};
 use get_json::get_json;
-use get_tasks::{get_all_tasks, get_one_task};
+use get_tasks::{get_all_tasks, get_assigned_to_me, get_one_task, get_trash};
 use hello_world::hello_world;
+use jwks::jwks;
+use login_lockouts::unlock_login;
+use mfa::{confirm_totp, disable_totp, enroll_totp, login_mfa};
 use middleware_user_session::user_session;
+use middleware_verified_user::verified_user;
 use mirror_body_json::mirror_body_json;
 use mirror_body_string::mirror_body_string;
 use mirror_custom_header::mirror_custom_header;
 use mirror_user_agent::mirror_user_agent;
+use oidc_login::{oidc_authorize, oidc_callback};
 use partial_update_task::partial_update;
 use partial_update_user::partial_update_user;
+use password_reset::{forgot_password, reset_password};
 use path_variables::{hard_coded_path, path_variables};
+use project_members::{
+    accept_invitation, create_project_invitation, decline_invitation,
+    delete_project_invitation, delete_project_member, get_my_invitations,
+    get_project_invitations, get_project_members, update_project_member,
+};
+use projects::{
+    create_project, delete_project, get_project, get_project_tasks,
+    get_projects, update_project,
+};
 use query_params::query_params;
+use refresh_token::refresh;
+use restore_user::restore_user;
 use returns_201::returns_201;
+use sessions::{delete_my_session, get_my_sessions};
 use set_middleware_custom_header::set_middleware_custom_header;
-use tower_http::cors::{Any, CorsLayer};
+use task_items::{create_item, delete_item, get_items, update_item};
+use task_status::{complete_task, uncomplete_task};
+use tower_http::cors::CorsLayer;
 use update_tasks::atomic_update;
 use users::{create_user, get_all_users, get_one_user, login, logout};
 use validate_json::validate_json;
+use verify_email::{resend_verification, verify_email};
 
 use self::{
     middleware_message::middleware_message,
@@ -70,14 +119,90 @@ pub struct SharedData {
 }
 
 pub async fn create_routes(app_state: AppState) -> Router {
+    // Credentials only work with an explicit origin, which is the frontend
+    // the auth cookie is meant for.
+    let frontend_origin = app_state
+        .config
+        .app_url
+        .trim_end_matches('/')
+        .parse::<HeaderValue>()
+        .expect("APP_URL is not a valid origin");
     let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST])
-        .allow_origin(Any);
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            CONTENT_TYPE,
+            AUTHORIZATION,
+            HeaderName::from_static(CSRF_HEADER),
+        ])
+        .allow_origin(frontend_origin)
+        .allow_credentials(true);
 
     let shared_data = SharedData {
         message: "Hello from shared data, I'm a State now".to_owned(),
     };
 
+    // task routes are only open to users that confirmed their email
+    let task_routes = Router::new()
+        .route("/tasks", post(create_task))
+        .route("/tasks", get(get_all_tasks))
+        .route("/tasks/trash", get(get_trash))
+        .route("/tasks/assigned_to_me", get(get_assigned_to_me))
+        .route("/tasks/:task_id", get(get_one_task))
+        .route("/tasks/:task_id", put(atomic_update))
+        .route("/tasks/:task_id", patch(partial_update))
+        .route("/tasks/:task_id", delete(delete_task))
+        .route("/tasks/:task_id/restore", post(restore_task))
+        .route("/tasks/:task_id/complete", post(complete_task))
+        .route("/tasks/:task_id/uncomplete", post(uncomplete_task))
+        .route("/tasks/:task_id/assign", post(assign_task))
+        .route("/tasks/:task_id/items", get(get_items))
+        .route("/tasks/:task_id/items", post(create_item))
+        .route("/tasks/:task_id/items/:item_id", patch(update_item))
+        .route("/tasks/:task_id/items/:item_id", delete(delete_item))
+        .route("/projects", post(create_project))
+        .route("/projects", get(get_projects))
+        .route("/projects/:project_id", get(get_project))
+        .route("/projects/:project_id", patch(update_project))
+        .route("/projects/:project_id", delete(delete_project))
+        .route("/projects/:project_id/tasks", get(get_project_tasks))
+        .route("/projects/:project_id/members", get(get_project_members))
+        .route(
+            "/projects/:project_id/members/:user_id",
+            patch(update_project_member),
+        )
+        .route(
+            "/projects/:project_id/members/:user_id",
+            delete(delete_project_member),
+        )
+        .route(
+            "/projects/:project_id/invitations",
+            post(create_project_invitation),
+        )
+        .route(
+            "/projects/:project_id/invitations",
+            get(get_project_invitations),
+        )
+        .route(
+            "/projects/:project_id/invitations/:invitation_id",
+            delete(delete_project_invitation),
+        )
+        .route("/users/me/invitations", get(get_my_invitations))
+        .route(
+            "/invitations/:invitation_id/accept",
+            post(accept_invitation),
+        )
+        .route(
+            "/invitations/:invitation_id/decline",
+            post(decline_invitation),
+        )
+        .route_layer(middleware::from_fn(verified_user));
+
     Router::new()
         .route(
             "/read_middleware_custom_header",
@@ -85,22 +210,40 @@ pub async fn create_routes(app_state: AppState) -> Router {
         )
         .route_layer(middleware::from_fn(set_middleware_custom_header))
         .route("/users/logout", post(logout))
-        .route("/tasks", post(create_task))
-        .route("/tasks", get(get_all_tasks))
-        .route("/tasks/:task_id", get(get_one_task))
-        .route("/tasks/:task_id", put(atomic_update))
-        .route("/tasks/:task_id", patch(partial_update))
-        .route("/tasks/:task_id", delete(delete_task))
+        .merge(task_routes)
         .route("/users", get(get_all_users))
+        .route("/users/me/sessions", get(get_my_sessions))
+        .route("/users/me/sessions/:session_id", delete(delete_my_session))
+        .route("/users/me", get(get_me))
+        .route("/users/me", patch(update_me))
+        .route("/users/me", delete(delete_me))
+        .route("/users/me/tokens", post(create_my_token))
+        .route("/users/me/tokens", get(get_my_tokens))
+        .route("/users/me/tokens/:token_id", get(get_my_token))
+        .route("/users/me/tokens/:token_id", delete(delete_my_token))
+        .route("/users/me/mfa/totp", post(enroll_totp))
+        .route("/users/me/mfa/totp", delete(disable_totp))
+        .route("/users/me/mfa/totp/confirm", post(confirm_totp))
+        .route("/users/verify/resend", post(resend_verification))
+        .route("/users/unlock", post(unlock_login))
         .route("/users/:user_id", get(get_one_user))
         .route("/users/:user_id", patch(partial_update_user))
+        .route("/users/:user_id/restore", post(restore_user))
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
             user_session,
         ))
         .route("/", get(hello_world))
+        .route("/.well-known/jwks.json", get(jwks))
         .route("/users", post(create_user))
         .route("/users/login", post(login))
+        .route("/users/login/mfa", post(login_mfa))
+        .route("/users/login/oidc/:provider", get(oidc_authorize))
+        .route("/users/login/oidc/:provider/callback", get(oidc_callback))
+        .route("/users/refresh", post(refresh))
+        .route("/users/verify", post(verify_email))
+        .route("/users/password/forgot", post(forgot_password))
+        .route("/users/password/reset", post(reset_password))
         .route("/mirror_body_string", post(mirror_body_string))
         .route("/mirror_body_json", post(mirror_body_json))
         .route("/path_variables/15", get(hard_coded_path))
@@ -0,0 +1,233 @@
+use crate::{
+    database::users::{self, Model as UserModel},
+    queires::{
+        access_token_queries::delete_user_access_tokens,
+        identity_queries::{create_identity, find_identity},
+        project_queries::create_inbox,
+        recovery_code_queries::delete_recovery_codes,
+        refresh_token_queries::revoke_user_refresh_tokens,
+        session_queries::revoke_all_sessions,
+        user_queries::{find_by_id, save_active_user, try_find_by_username},
+    },
+    utils::{
+        app_error::AppError,
+        auth_cookies::AuthCookies,
+        client_info::ClientInfo,
+        jwt::JwtIssuer,
+        login_throttle::SharedLoginThrottle,
+        oidc::{IdTokenClaims, OidcProvider, OidcProviders},
+        password_hasher::PasswordHasher,
+        role::Role,
+        secure_token::generate_token,
+    },
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Redirect,
+    Json,
+};
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::Deserialize;
+use tower_cookies::{
+    cookie::{time::Duration, SameSite},
+    Cookie, Cookies,
+};
+
+use super::users::{finish_first_factor, ResponseUser};
+
+const COOKIE_PATH: &str = "/users/login/oidc";
+const STATE_COOKIE: &str = "oidc-state";
+const NONCE_COOKIE: &str = "oidc-nonce";
+const VERIFIER_COOKIE: &str = "oidc-verifier";
+
+#[derive(Deserialize)]
+pub struct RequestOidcCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+fn find_provider(
+    providers: &OidcProviders, name: &str,
+) -> Result<OidcProvider, AppError> {
+    providers
+        .0
+        .get(name)
+        .cloned()
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))
+}
+
+/// The values the callback has to match live in short-lived cookies that
+/// only the login routes ever see.
+fn flow_cookie(
+    name: &'static str, value: String, secure: bool,
+) -> Cookie<'static> {
+    Cookie::build(name, value)
+        .path(COOKIE_PATH)
+        .http_only(true)
+        .secure(secure)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::minutes(10))
+        .finish()
+}
+
+fn take_flow_cookie(cookies: &Cookies, name: &'static str) -> Option<String> {
+    let value = cookies.get(name).map(|cookie| cookie.value().to_owned());
+    cookies.remove(Cookie::build(name, "").path(COOKIE_PATH).finish());
+    value
+}
+
+/// Sends the browser to the provider to sign in.
+pub async fn oidc_authorize(
+    Path(provider): Path<String>, cookies: Cookies,
+    State(providers): State<OidcProviders>,
+) -> Result<Redirect, AppError> {
+    let provider = find_provider(&providers, &provider)?;
+    let metadata = provider.discover().await?;
+
+    let state = generate_token();
+    let nonce = generate_token();
+    let pkce_verifier = generate_token();
+    let url = provider.authorization_url(
+        &metadata,
+        &state,
+        &nonce,
+        &pkce_verifier,
+    )?;
+
+    let secure = provider.redirect_url.starts_with("https://");
+    cookies.add(flow_cookie(STATE_COOKIE, state, secure));
+    cookies.add(flow_cookie(NONCE_COOKIE, nonce, secure));
+    cookies.add(flow_cookie(VERIFIER_COOKIE, pkce_verifier, secure));
+
+    Ok(Redirect::to(&url))
+}
+
+/// Where the provider sends the browser back to. Logs in the user linked to
+/// the external account, linking or creating one on the first visit.
+#[allow(clippy::too_many_arguments)]
+pub async fn oidc_callback(
+    Path(provider): Path<String>, Query(params): Query<RequestOidcCallback>,
+    cookies: AuthCookies, client: ClientInfo,
+    State(db): State<DatabaseConnection>, State(jwt): State<JwtIssuer>,
+    State(throttle): State<SharedLoginThrottle>,
+    State(hasher): State<PasswordHasher>,
+    State(providers): State<OidcProviders>,
+) -> Result<Json<ResponseUser>, AppError> {
+    let provider = find_provider(&providers, &provider)?;
+
+    let state = take_flow_cookie(cookies.jar(), STATE_COOKIE);
+    let nonce = take_flow_cookie(cookies.jar(), NONCE_COOKIE);
+    let pkce_verifier = take_flow_cookie(cookies.jar(), VERIFIER_COOKIE);
+
+    if let Some(error) = params.error {
+        eprintln!("Identity provider {} returned {:?}", provider.name, error);
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "sign in with the identity provider was not completed",
+        ));
+    }
+
+    let (Some(code), Some(nonce), Some(pkce_verifier)) =
+        (params.code, nonce, pkce_verifier)
+    else {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid or expired login attempt",
+        ));
+    };
+
+    if state.is_none() || params.state != state {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid or expired login attempt",
+        ));
+    }
+
+    let metadata = provider.discover().await?;
+    let claims = provider
+        .exchange_code(&metadata, &code, &pkce_verifier, &nonce)
+        .await?;
+
+    let user = resolve_user(&db, &hasher, &provider, claims).await?;
+
+    finish_first_factor(&cookies, &client, &db, &jwt, &throttle, user).await
+}
+
+/// Hands an account that never proved its email over to whoever the
+/// provider vouches for. Someone else may have signed up with the address,
+/// so everything they could have set up to keep access is dropped: the
+/// password, two-factor authentication, sessions and access tokens.
+async fn claim_unverified_account(
+    db: &DatabaseConnection, hasher: &PasswordHasher, user: UserModel,
+) -> Result<UserModel, AppError> {
+    let user_id = user.id;
+    let mut user = user.into_active_model();
+    user.password = Set(hasher.hash(generate_token()).await?);
+    user.verified_at = Set(Some(Utc::now().into()));
+    user.totp_secret = Set(None);
+    user.totp_enabled_at = Set(None);
+    let user = save_active_user(db, user).await?;
+
+    delete_recovery_codes(db, user_id).await?;
+    revoke_all_sessions(db, user_id).await?;
+    revoke_user_refresh_tokens(db, user_id).await?;
+    delete_user_access_tokens(db, user_id).await?;
+
+    Ok(user)
+}
+
+async fn resolve_user(
+    db: &DatabaseConnection, hasher: &PasswordHasher, provider: &OidcProvider,
+    claims: IdTokenClaims,
+) -> Result<UserModel, AppError> {
+    if let Some(identity) =
+        find_identity(db, &provider.name, &claims.sub).await?
+    {
+        return find_by_id(db, identity.user_id).await;
+    }
+
+    let Some(email) = claims.email else {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "the identity provider did not share an email address",
+        ));
+    };
+    let email_verified = claims.email_verified == Some(true);
+
+    let user = match try_find_by_username(db, &email).await? {
+        // Only link an existing account when the provider vouches for the
+        // address, otherwise anyone could take it over by claiming it.
+        Some(user) if email_verified && user.verified_at.is_some() => user,
+        Some(user) if email_verified => {
+            claim_unverified_account(db, hasher, user).await?
+        }
+        Some(_) => {
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                "an account with this email already exists",
+            ))
+        }
+        None => {
+            let new_user = users::ActiveModel {
+                username: Set(email.clone()),
+                // Never shared with anyone, a password can be set through
+                // the reset flow if the user ever wants one.
+                password: Set(hasher.hash(generate_token()).await?),
+                role: Set(Role::User.as_str().to_owned()),
+                verified_at: Set(email_verified.then(|| Utc::now().into())),
+                ..Default::default()
+            };
+            let user = save_active_user(db, new_user).await?;
+            create_inbox(db, user.id).await?;
+            user
+        }
+    };
+
+    create_identity(db, user.id, &provider.name, &claims.sub, Some(email))
+        .await?;
+
+    Ok(user)
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use crate::{
+    database::{
+        access_tokens::{Entity as AccessTokens, Model as AccessTokenModel},
+        sessions::{Entity as Sessions, Model as SessionModel},
+        users::{self, Entity as Users, Model as UserModel},
+    },
+    utils::{app_error::AppError, role::is_admin},
+};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+
+/// A row that belongs to a user and can only be changed by that user.
+#[async_trait]
+pub trait OwnedResource: Sized {
+    /// Name of the path variable that carries the resource id.
+    const PATH_PARAM: &'static str;
+
+    /// Whether admins may act on other users' rows. Rows served under
+    /// `/users/me` belong to the caller alone, even for admins.
+    const ADMIN_ACCESS: bool = false;
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr>;
+
+    fn is_owned_by(&self, user: &UserModel) -> bool;
+}
+
+#[async_trait]
+impl OwnedResource for UserModel {
+    const PATH_PARAM: &'static str = "user_id";
+    const ADMIN_ACCESS: bool = true;
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Users::find_by_id(id)
+            .filter(users::Column::DeletedAt.is_null())
+            .one(db)
+            .await
+    }
+
+    fn is_owned_by(&self, user: &UserModel) -> bool {
+        self.id == user.id
+    }
+}
+
+#[async_trait]
+impl OwnedResource for SessionModel {
+    const PATH_PARAM: &'static str = "session_id";
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Sessions::find_by_id(id).one(db).await
+    }
+
+    fn is_owned_by(&self, user: &UserModel) -> bool {
+        self.user_id == user.id
+    }
+}
+
+#[async_trait]
+impl OwnedResource for AccessTokenModel {
+    const PATH_PARAM: &'static str = "token_id";
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        AccessTokens::find_by_id(id).one(db).await
+    }
+
+    fn is_owned_by(&self, user: &UserModel) -> bool {
+        self.user_id == user.id
+    }
+}
+
+/// Loads the resource named in the path and only lets the request through
+/// when the logged in user owns it, or is an admin where
+/// [`OwnedResource::ADMIN_ACCESS`] allows: 404 when it does not exist or
+/// is someone else's `/users/me` row, 403 when it belongs to someone else.
+/// Must run behind `user_session`.
+pub struct Owned<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequestParts<S> for Owned<T>
+where
+    DatabaseConnection: FromRef<S>,
+    S: Send + Sync,
+    T: OwnedResource + Send,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts, state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let user =
+            parts
+                .extensions
+                .get::<UserModel>()
+                .cloned()
+                .ok_or_else(|| {
+                    AppError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "You are not authorized, please login or create account",
+                )
+                })?;
+
+        let Path(params) =
+            Path::<HashMap<String, String>>::from_request_parts(parts, state)
+                .await
+                .map_err(|error| {
+                    eprintln!("Error extracting path: {:?}", error);
+                    AppError::new(StatusCode::BAD_REQUEST, "invalid path")
+                })?;
+        let id = params
+            .get(T::PATH_PARAM)
+            .and_then(|id| id.parse::<i32>().ok())
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))?;
+
+        let db = DatabaseConnection::from_ref(state);
+        let resource = T::find(&db, id)
+            .await
+            .map_err(|error| {
+                eprintln!("Error getting owned resource: {:?}", error);
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error",
+                )
+            })?
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))?;
+
+        if resource.is_owned_by(&user) {
+            return Ok(Owned(resource));
+        }
+
+        // Like `ProjectAccess`, other users cannot tell the row exists.
+        if !T::ADMIN_ACCESS {
+            return Err(AppError::new(StatusCode::NOT_FOUND, "not found"));
+        }
+
+        if !is_admin(&user) {
+            return Err(AppError::new(
+                StatusCode::FORBIDDEN,
+                "You are not allowed to modify this resource",
+            ));
+        }
+
+        Ok(Owned(resource))
+    }
+}
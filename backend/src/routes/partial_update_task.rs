@@ -2,12 +2,24 @@
 ** Partial Updates
 */
 
-use crate::database::{tasks, tasks::Entity as Tasks};
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
+use super::{
+    assign_task::can_be_assigned,
+    project_access::{
+        require_project_role, require_unarchived, AnyTask, Editor,
+        ProjectAccess,
+    },
 };
+use crate::{
+    database::{tasks, tasks::Entity as Tasks, users::Model as UserModel},
+    queires::{
+        task_item_queries::sync_task_items_deleted_at,
+        task_queries::change_status,
+    },
+    utils::{
+        project_role::ProjectRole, role::is_admin, task_status::TaskStatus,
+    },
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
 use sea_orm::{
     prelude::DateTimeWithTimeZone, ColumnTrait, DatabaseConnection,
     EntityTrait, IntoActiveModel, QueryFilter, Set,
@@ -28,34 +40,51 @@ pub struct RequestTask {
         skip_serializing_if = "Option::is_none",    // <- important for serialization
         with = "::serde_with::rust::double_option",
     )]
-    pub completed_at: Option<Option<DateTimeWithTimeZone>>,
+    pub description: Option<Option<String>>,
     #[serde(
         default,                                    // <- important for deserialization
         skip_serializing_if = "Option::is_none",    // <- important for serialization
         with = "::serde_with::rust::double_option",
     )]
-    pub description: Option<Option<String>>,
+    pub deleted_at: Option<Option<DateTimeWithTimeZone>>,
     #[serde(
         default,                                    // <- important for deserialization
         skip_serializing_if = "Option::is_none",    // <- important for serialization
         with = "::serde_with::rust::double_option",
     )]
-    pub deleted_at: Option<Option<DateTimeWithTimeZone>>,
+    pub due_at: Option<Option<DateTimeWithTimeZone>>,
+    /// Setting it again re-arms a reminder that already fired.
+    #[serde(
+        default,                                    // <- important for deserialization
+        skip_serializing_if = "Option::is_none",    // <- important for serialization
+        with = "::serde_with::rust::double_option",
+    )]
+    pub remind_at: Option<Option<DateTimeWithTimeZone>>,
+    /// Also stamps or clears `completed_at`, see [`change_status`].
+    pub status: Option<TaskStatus>,
+    /// Moves the task to another of the user's projects.
+    pub project_id: Option<i32>,
 }
 
 pub async fn partial_update(
-    Path(task_id): Path<i32>, State(database): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: AnyTask(task),
+        ..
+    }: ProjectAccess<AnyTask, Editor>,
+    Extension(user): Extension<UserModel>,
+    State(database): State<DatabaseConnection>,
     Json(request_task): Json<RequestTask>,
 ) -> Result<(), StatusCode> {
-    let mut db_task = if let Some(task) = Tasks::find_by_id(task_id)
-        .one(&database)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    {
-        task.into_active_model()
-    } else {
+    // A trashed task is not found, except by admins restoring it.
+    let restoring = matches!(request_task.deleted_at, Some(None));
+    if task.deleted_at.is_some() && !(restoring && is_admin(&user)) {
         return Err(StatusCode::NOT_FOUND);
-    };
+    }
+
+    let task_id = task.id;
+    let current_status = TaskStatus::of(&task);
+    let previous_deleted_at = task.deleted_at;
+    let mut db_task = task.clone().into_active_model();
 
     if let Some(priority) = request_task.priority {
         db_task.priority = Set(priority);
@@ -65,10 +94,6 @@ pub async fn partial_update(
         db_task.title = Set(title);
     }
 
-    if let Some(completed_at) = request_task.completed_at {
-        db_task.completed_at = Set(completed_at);
-    }
-
     if let Some(description) = request_task.description {
         db_task.description = Set(description);
     }
@@ -77,11 +102,66 @@ pub async fn partial_update(
         db_task.deleted_at = Set(deleted_at);
     }
 
+    if let Some(due_at) = request_task.due_at {
+        db_task.due_at = Set(due_at);
+    }
+
+    if let Some(remind_at) = request_task.remind_at {
+        db_task.remind_at = Set(remind_at);
+        db_task.reminded_at = Set(None);
+    }
+
+    if let Some(project_id) = request_task.project_id {
+        let project = require_project_role(
+            &database,
+            &user,
+            project_id,
+            ProjectRole::Editor,
+        )
+        .await
+        .map_err(|error| error.code())?;
+        if task.project_id != Some(project.id) {
+            require_unarchived(&project).map_err(|error| error.code())?;
+        }
+        db_task.project_id = Set(Some(project.id));
+
+        // Someone who cannot edit tasks in the new project loses the task.
+        if let Some(assignee_id) = task.assignee_id {
+            let assignable = can_be_assigned(
+                &database,
+                &task,
+                Some(project.id),
+                assignee_id,
+            )
+            .await
+            .map_err(|error| error.code())?;
+            if !assignable {
+                db_task.assignee_id = Set(None);
+            }
+        }
+    }
+
+    if let Some(status) = request_task.status {
+        change_status(&mut db_task, current_status, status)
+            .map_err(|_| StatusCode::CONFLICT)?;
+    }
+
     Tasks::update(db_task)
         .filter(tasks::Column::Id.eq(task_id))
         .exec(&database)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if let Some(deleted_at) = request_task.deleted_at {
+        sync_task_items_deleted_at(
+            &database,
+            task_id,
+            previous_deleted_at,
+            deleted_at,
+        )
+        .await
+        .map_err(|error| error.code())?;
+    }
+
     Ok(())
 }
@@ -1,13 +1,23 @@
 /*
 ** Partial Updates Users
 */
-use crate::database::users::{self, Entity as Users};
+use super::owned_resource::Owned;
+use crate::{
+    database::users::{self, Entity as Users, Model as UserModel},
+    queires::{
+        access_token_queries::delete_user_access_tokens,
+        refresh_token_queries::revoke_user_refresh_tokens,
+        session_queries::revoke_all_sessions,
+        task_queries::soft_delete_user_tasks,
+    },
+    utils::{password_hasher::PasswordHasher, role::is_admin},
+};
 use axum::{
     async_trait,
     body::HttpBody,
-    extract::{FromRequest, Path, State},
+    extract::{FromRequest, State},
     http::{Request, StatusCode},
-    BoxError, Json, RequestExt,
+    BoxError, Extension, Json, RequestExt,
 };
 use sea_orm::{
     prelude::DateTimeWithTimeZone, ColumnTrait, DatabaseConnection,
@@ -56,30 +66,41 @@ where
 }
 
 pub async fn partial_update_user(
-    Path(user_id): Path<i32>, State(db): State<DatabaseConnection>,
+    Owned(db_user): Owned<UserModel>,
+    Extension(current_user): Extension<UserModel>,
+    State(db): State<DatabaseConnection>, State(hasher): State<PasswordHasher>,
     user: RequestUser,
 ) -> Result<(), StatusCode> {
-    let mut db_user = if let Some(user) = Users::find_by_id(user_id)
-        .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    // Soft-deleting or restoring accounts is reserved for admins, and users
+    // change their own credentials through `/users/me`, which asks for the
+    // current password first.
+    if (user.deleted_at.is_some()
+        || user.username.is_some()
+        || user.password.is_some())
+        && !is_admin(&current_user)
     {
-        user.into_active_model()
-    } else {
-        return Err(StatusCode::NOT_FOUND);
-    };
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let user_id = db_user.id;
+    let password_changed = user.password.is_some();
+    let mut db_user = db_user.into_active_model();
 
     if let Some(username) = user.username {
         db_user.username = Set(username);
     }
 
     if let Some(password) = user.password {
-        db_user.password = Set(hash_password(password)?);
+        db_user.password = Set(hasher
+            .hash(password)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?);
     }
 
     if let Some(deleted_at) = user.deleted_at {
         db_user.deleted_at = Set(deleted_at);
     }
+    let deleted_at = user.deleted_at.flatten();
 
     Users::update(db_user)
         .filter(users::Column::Id.eq(user_id))
@@ -87,10 +108,27 @@ pub async fn partial_update_user(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(())
-}
+    // Deleting an account takes its tasks with it, like `DELETE /users/me`
+    // does.
+    if let Some(deleted_at) = deleted_at {
+        soft_delete_user_tasks(&db, user_id, deleted_at)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
 
-fn hash_password(password: String) -> Result<String, StatusCode> {
-    bcrypt::hash(password, 14)
-        .map_err(|_error| StatusCode::INTERNAL_SERVER_ERROR)
+    // A deleted account or a password set by an admin leaves nothing the
+    // user, or whoever took the account over, could still sign in with.
+    if deleted_at.is_some() || password_changed {
+        revoke_all_sessions(&db, user_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        revoke_user_refresh_tokens(&db, user_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        delete_user_access_tokens(&db, user_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    Ok(())
 }
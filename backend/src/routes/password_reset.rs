@@ -0,0 +1,108 @@
+use crate::{
+    app_state::AppConfig,
+    queires::{
+        access_token_queries::delete_user_access_tokens,
+        one_time_token_queries::{
+            consume_one_time_token, create_one_time_token, TokenPurpose,
+        },
+        refresh_token_queries::revoke_user_refresh_tokens,
+        session_queries::revoke_all_sessions,
+        user_queries::{find_by_id, find_by_username, save_active_user},
+    },
+    utils::{
+        app_error::AppError,
+        mailer::{Email, SharedMailer},
+        password_hasher::PasswordHasher,
+    },
+};
+use axum::{extract::State, http::StatusCode, Json};
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize)]
+pub struct RequestForgotPassword {
+    pub username: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RequestResetPassword {
+    pub token: String,
+    #[validate(length(min = 8, message = "must have at least 8 characters"))]
+    pub password: String,
+}
+
+#[derive(Serialize)]
+pub struct ResponseMessage {
+    message: String,
+}
+
+pub async fn forgot_password(
+    State(db): State<DatabaseConnection>, State(mailer): State<SharedMailer>,
+    State(config): State<AppConfig>,
+    Json(request): Json<RequestForgotPassword>,
+) -> (StatusCode, Json<ResponseMessage>) {
+    // Unknown accounts get the exact same answer, and the work happens in
+    // the background, so neither the body nor the timing says whether the
+    // address is registered.
+    tokio::spawn(async move {
+        let Ok(user) = find_by_username(&db, request.username).await else {
+            return;
+        };
+        let Ok(token) =
+            create_one_time_token(&db, user.id, TokenPurpose::ResetPassword)
+                .await
+        else {
+            return;
+        };
+        let email = Email {
+            to: user.username,
+            subject: "Reset your password".to_owned(),
+            body: format!(
+                "Someone asked to reset the password of your account. If it \
+                 was you, open the link below within the next hour. If not, \
+                 you can ignore this email.\n\n{}/reset-password?token={}\n",
+                config.app_url, token
+            ),
+        };
+
+        if let Err(error) = mailer.0.send(email).await {
+            eprintln!("Error sending password reset email: {:?}", error);
+        }
+    });
+
+    (
+        StatusCode::ACCEPTED,
+        Json(ResponseMessage {
+            message: "If that account exists, we sent it a link to reset the \
+                      password"
+                .to_owned(),
+        }),
+    )
+}
+
+pub async fn reset_password(
+    State(db): State<DatabaseConnection>, State(hasher): State<PasswordHasher>,
+    Json(request): Json<RequestResetPassword>,
+) -> Result<StatusCode, AppError> {
+    if let Err(errors) = request.validate() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, errors.to_string()));
+    }
+
+    let token = consume_one_time_token(
+        &db,
+        &request.token,
+        TokenPurpose::ResetPassword,
+    )
+    .await?;
+
+    let mut user = find_by_id(&db, token.user_id).await?.into_active_model();
+    user.password = Set(hasher.hash(request.password).await?);
+    save_active_user(&db, user).await?;
+
+    revoke_all_sessions(&db, token.user_id).await?;
+    revoke_user_refresh_tokens(&db, token.user_id).await?;
+    delete_user_access_tokens(&db, token.user_id).await?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use crate::{
+    database::{
+        projects::{Entity as Projects, Model as ProjectModel},
+        tasks::{self, Entity as Tasks, Model as TaskModel},
+        users::Model as UserModel,
+    },
+    queires::{
+        project_member_queries::find_project_role,
+        project_queries::find_project_by_id,
+    },
+    utils::{app_error::AppError, project_role::ProjectRole, role::is_admin},
+};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+
+/// Marker for the project role a route requires, used as
+/// `ProjectAccess<TaskModel, Editor>`.
+pub trait ProjectRoleRequirement {
+    const ROLE: ProjectRole;
+}
+
+pub struct Viewer;
+pub struct Editor;
+pub struct Owner;
+
+impl ProjectRoleRequirement for Viewer {
+    const ROLE: ProjectRole = ProjectRole::Viewer;
+}
+
+impl ProjectRoleRequirement for Editor {
+    const ROLE: ProjectRole = ProjectRole::Editor;
+}
+
+impl ProjectRoleRequirement for Owner {
+    const ROLE: ProjectRole = ProjectRole::Owner;
+}
+
+/// A row that lives in a project, access to it follows from the user's role
+/// in that project.
+#[async_trait]
+pub trait ProjectResource: Sized {
+    /// Name of the path variable that carries the resource id.
+    const PATH_PARAM: &'static str;
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr>;
+
+    fn project_id(&self) -> Option<i32>;
+
+    /// Who has full access when the row is in no project, like tasks
+    /// created before there were projects.
+    fn owner_id(&self) -> Option<i32>;
+}
+
+#[async_trait]
+impl ProjectResource for TaskModel {
+    const PATH_PARAM: &'static str = "task_id";
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Tasks::find_by_id(id)
+            .filter(tasks::Column::DeletedAt.is_null())
+            .one(db)
+            .await
+    }
+
+    fn project_id(&self) -> Option<i32> {
+        self.project_id
+    }
+
+    fn owner_id(&self) -> Option<i32> {
+        self.user_id
+    }
+}
+
+/// A task whether or not it is in the trash, for the few routes that act on
+/// trashed tasks, like restoring them. Everywhere else a trashed task is not
+/// found.
+pub struct AnyTask(pub TaskModel);
+
+#[async_trait]
+impl ProjectResource for AnyTask {
+    const PATH_PARAM: &'static str = "task_id";
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Ok(Tasks::find_by_id(id).one(db).await?.map(AnyTask))
+    }
+
+    fn project_id(&self) -> Option<i32> {
+        self.0.project_id
+    }
+
+    fn owner_id(&self) -> Option<i32> {
+        self.0.user_id
+    }
+}
+
+#[async_trait]
+impl ProjectResource for ProjectModel {
+    const PATH_PARAM: &'static str = "project_id";
+
+    async fn find(
+        db: &DatabaseConnection, id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Projects::find_by_id(id).one(db).await
+    }
+
+    fn project_id(&self) -> Option<i32> {
+        Some(self.id)
+    }
+
+    fn owner_id(&self) -> Option<i32> {
+        Some(self.user_id)
+    }
+}
+
+/// The user's role in a project, admins being treated as owners everywhere.
+pub async fn project_role(
+    db: &DatabaseConnection, user: &UserModel, project: &ProjectModel,
+) -> Result<Option<ProjectRole>, AppError> {
+    if is_admin(user) {
+        return Ok(Some(ProjectRole::Owner));
+    }
+
+    find_project_role(db, project, user.id).await
+}
+
+fn forbidden() -> AppError {
+    AppError::new(
+        StatusCode::FORBIDDEN,
+        "You are not allowed to modify this resource",
+    )
+}
+
+/// Loads the project and fails unless the user has at least the role in it,
+/// e.g. before adding a task to it. Projects the user is not in are
+/// reported as missing.
+pub async fn require_project_role(
+    db: &DatabaseConnection, user: &UserModel, project_id: i32,
+    required: ProjectRole,
+) -> Result<ProjectModel, AppError> {
+    let project = find_project_by_id(db, project_id).await?;
+
+    match project_role(db, user, &project).await? {
+        Some(role) if role >= required => Ok(project),
+        Some(_) => Err(forbidden()),
+        None => Err(AppError::new(StatusCode::NOT_FOUND, "not found")),
+    }
+}
+
+/// Fails with 409 when the project is archived, which takes no new tasks,
+/// whether created in it or moved there.
+pub fn require_unarchived(project: &ProjectModel) -> Result<(), AppError> {
+    if project.archived {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "the project is archived",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Loads the resource named in the path and only lets the request through
+/// when the logged in user has at least role `R` in its project: 404 when it
+/// does not exist or the user has no role in the project, 403 when the
+/// user's role falls short. Must run behind
+/// `user_session`.
+pub struct ProjectAccess<T, R> {
+    pub resource: T,
+    /// The user's role in the project, at least `R`.
+    pub role: ProjectRole,
+    _requirement: PhantomData<R>,
+}
+
+#[async_trait]
+impl<S, T, R> FromRequestParts<S> for ProjectAccess<T, R>
+where
+    DatabaseConnection: FromRef<S>,
+    S: Send + Sync,
+    T: ProjectResource + Send,
+    R: ProjectRoleRequirement,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts, state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let user =
+            parts
+                .extensions
+                .get::<UserModel>()
+                .cloned()
+                .ok_or_else(|| {
+                    AppError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "You are not authorized, please login or create account",
+                )
+                })?;
+
+        let Path(params) =
+            Path::<HashMap<String, String>>::from_request_parts(parts, state)
+                .await
+                .map_err(|error| {
+                    eprintln!("Error extracting path: {:?}", error);
+                    AppError::new(StatusCode::BAD_REQUEST, "invalid path")
+                })?;
+        let id = params
+            .get(T::PATH_PARAM)
+            .and_then(|id| id.parse::<i32>().ok())
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))?;
+
+        let db = DatabaseConnection::from_ref(state);
+        let resource = T::find(&db, id)
+            .await
+            .map_err(|error| {
+                eprintln!("Error getting project resource: {:?}", error);
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error",
+                )
+            })?
+            .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))?;
+
+        let role = match resource.project_id() {
+            Some(project_id) => {
+                let project = find_project_by_id(&db, project_id).await?;
+                project_role(&db, &user, &project).await?
+            }
+            None if resource.owner_id() == Some(user.id) || is_admin(&user) => {
+                Some(ProjectRole::Owner)
+            }
+            None => None,
+        };
+
+        // Like `require_project_role`, outsiders cannot tell the resource
+        // exists.
+        match role {
+            Some(role) if role >= R::ROLE => Ok(ProjectAccess {
+                resource,
+                role,
+                _requirement: PhantomData,
+            }),
+            Some(_) => Err(forbidden()),
+            None => Err(AppError::new(StatusCode::NOT_FOUND, "not found")),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use super::project_access::{Owner, ProjectAccess, Viewer};
+use crate::{
+    app_state::AppConfig,
+    database::{
+        project_invitations::{self, Model as ProjectInvitationModel},
+        projects::Model as ProjectModel,
+        users::Model as UserModel,
+    },
+    queires::{
+        project_invitation_queries::{
+            create_project_invitation as create_invitation,
+            delete_project_invitation as delete_invitation,
+            find_pending_invitation, find_pending_invitations_for,
+            find_pending_project_invitations, save_active_invitation,
+        },
+        project_member_queries::{
+            delete_project_member as delete_member, find_membership,
+            find_project_members, set_project_member,
+        },
+        project_queries::find_project_by_id,
+        task_queries::unassign_project_tasks,
+        user_queries::{find_users_by_ids, try_find_by_username},
+    },
+    utils::{
+        app_error::AppError,
+        mailer::{Email, SharedMailer},
+        project_role::ProjectRole,
+    },
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize)]
+pub struct MemberPath {
+    pub user_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationPath {
+    pub invitation_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct RequestUpdateMember {
+    pub role: ProjectRole,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RequestInvitation {
+    #[validate(email(message = "must be a valid email"))]
+    pub email: String,
+    pub role: ProjectRole,
+}
+
+#[derive(Serialize)]
+pub struct ResponseMember {
+    user_id: i32,
+    username: String,
+    role: ProjectRole,
+}
+
+#[derive(Serialize)]
+pub struct ResponseInvitation {
+    id: i32,
+    project_id: i32,
+    email: String,
+    role: ProjectRole,
+    expires_at: DateTime<FixedOffset>,
+    created_at: DateTime<FixedOffset>,
+}
+
+impl From<ProjectInvitationModel> for ResponseInvitation {
+    fn from(invitation: ProjectInvitationModel) -> Self {
+        Self {
+            id: invitation.id,
+            project_id: invitation.project_id,
+            role: ProjectRole::parse(&invitation.role),
+            email: invitation.email,
+            expires_at: invitation.expires_at,
+            created_at: invitation.created_at,
+        }
+    }
+}
+
+fn creator_error() -> AppError {
+    AppError::new(
+        StatusCode::BAD_REQUEST,
+        "the user who created the project is always an owner",
+    )
+}
+
+/// Everyone in the project, its creator first.
+pub async fn get_project_members(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: project, ..
+    }: ProjectAccess<ProjectModel, Viewer>,
+) -> Result<Json<Vec<ResponseMember>>, AppError> {
+    let members = find_project_members(&db, project.id).await?;
+
+    let mut roles = vec![(project.user_id, ProjectRole::Owner)];
+    roles.extend(
+        members
+            .into_iter()
+            .map(|member| (member.user_id, ProjectRole::parse(&member.role))),
+    );
+
+    let ids = roles.iter().map(|(user_id, _)| *user_id).collect();
+    let usernames = find_users_by_ids(&db, ids)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user.username))
+        .collect::<HashMap<_, _>>();
+
+    // Deleted accounts drop out of the list.
+    let members = roles
+        .into_iter()
+        .filter_map(|(user_id, role)| {
+            let username = usernames.get(&user_id)?.clone();
+            Some(ResponseMember {
+                user_id,
+                username,
+                role,
+            })
+        })
+        .collect();
+
+    Ok(Json(members))
+}
+
+pub async fn update_project_member(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: project, ..
+    }: ProjectAccess<ProjectModel, Owner>,
+    Path(path): Path<MemberPath>, Json(request): Json<RequestUpdateMember>,
+) -> Result<StatusCode, AppError> {
+    if path.user_id == project.user_id {
+        return Err(creator_error());
+    }
+
+    if find_membership(&db, project.id, path.user_id)
+        .await?
+        .is_none()
+    {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "not found"));
+    }
+
+    set_project_member(&db, project.id, path.user_id, request.role).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Removes someone from the project. Owners can remove anyone but the
+/// creator, everyone else can only leave.
+pub async fn delete_project_member(
+    State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>,
+    ProjectAccess {
+        resource: project,
+        role,
+        ..
+    }: ProjectAccess<ProjectModel, Viewer>,
+    Path(path): Path<MemberPath>,
+) -> Result<StatusCode, AppError> {
+    if path.user_id == project.user_id {
+        return Err(creator_error());
+    }
+
+    if path.user_id != user.id && role != ProjectRole::Owner {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "You are not allowed to modify this resource",
+        ));
+    }
+
+    let member = find_membership(&db, project.id, path.user_id)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "not found"))?;
+    delete_member(&db, member).await?;
+    unassign_project_tasks(&db, project.id, path.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Invites someone by email, they can answer once they signed up with and
+/// verified that address.
+pub async fn create_project_invitation(
+    State(db): State<DatabaseConnection>, State(mailer): State<SharedMailer>,
+    State(config): State<AppConfig>, Extension(user): Extension<UserModel>,
+    ProjectAccess {
+        resource: project, ..
+    }: ProjectAccess<ProjectModel, Owner>,
+    Json(request): Json<RequestInvitation>,
+) -> Result<(StatusCode, Json<ResponseInvitation>), AppError> {
+    if let Err(errors) = request.validate() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, errors.to_string()));
+    }
+
+    if project.is_inbox {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "the inbox cannot be shared",
+        ));
+    }
+
+    if let Some(invitee) = try_find_by_username(&db, &request.email).await? {
+        let is_member = invitee.id == project.user_id
+            || find_membership(&db, project.id, invitee.id)
+                .await?
+                .is_some();
+        if is_member {
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                "this user is already in the project",
+            ));
+        }
+    }
+
+    let invitation = create_invitation(
+        &db,
+        project.id,
+        &request.email,
+        request.role,
+        user.id,
+    )
+    .await?;
+
+    let email = Email {
+        to: invitation.email.clone(),
+        subject: format!("You were invited to {}", project.name),
+        body: format!(
+            "{} invited you to the project \"{}\" as {}. The invitation \
+             expires in 7 days.\n\n{}/invitations\n",
+            user.username,
+            project.name,
+            request.role.as_str(),
+            config.app_url
+        ),
+    };
+
+    // The invitation is listed for the invitee either way, so a failed email
+    // should not fail the request.
+    if let Err(error) = mailer.0.send(email).await {
+        eprintln!("Error sending project invitation: {:?}", error);
+    }
+
+    Ok((StatusCode::CREATED, Json(invitation.into())))
+}
+
+pub async fn get_project_invitations(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: project, ..
+    }: ProjectAccess<ProjectModel, Owner>,
+) -> Result<Json<Vec<ResponseInvitation>>, AppError> {
+    let invitations = find_pending_project_invitations(&db, project.id)
+        .await?
+        .into_iter()
+        .map(ResponseInvitation::from)
+        .collect();
+
+    Ok(Json(invitations))
+}
+
+pub async fn delete_project_invitation(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: project, ..
+    }: ProjectAccess<ProjectModel, Owner>,
+    Path(path): Path<InvitationPath>,
+) -> Result<StatusCode, AppError> {
+    let invitation = find_pending_invitation(&db, path.invitation_id).await?;
+    if invitation.project_id != project.id {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "not found"));
+    }
+
+    delete_invitation(&db, invitation).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The pending invitations sent to the logged in user's email address.
+pub async fn get_my_invitations(
+    State(db): State<DatabaseConnection>, Extension(user): Extension<UserModel>,
+) -> Result<Json<Vec<ResponseInvitation>>, AppError> {
+    let invitations = find_pending_invitations_for(&db, &user.username)
+        .await?
+        .into_iter()
+        .map(ResponseInvitation::from)
+        .collect();
+
+    Ok(Json(invitations))
+}
+
+/// Finds a pending invitation addressed to the user. The email address was
+/// verified to get here, so it proves the invitation is theirs.
+async fn find_my_invitation(
+    db: &DatabaseConnection, user: &UserModel, invitation_id: i32,
+) -> Result<ProjectInvitationModel, AppError> {
+    let invitation = find_pending_invitation(db, invitation_id).await?;
+
+    if invitation.email != user.username.to_lowercase() {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "not found"));
+    }
+
+    Ok(invitation)
+}
+
+pub async fn accept_invitation(
+    State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>, Path(path): Path<InvitationPath>,
+) -> Result<StatusCode, AppError> {
+    let invitation = find_my_invitation(&db, &user, path.invitation_id).await?;
+    let project = find_project_by_id(&db, invitation.project_id).await?;
+    let role = ProjectRole::parse(&invitation.role);
+
+    if project.user_id != user.id {
+        set_project_member(&db, project.id, user.id, role).await?;
+    }
+
+    let mut invitation: project_invitations::ActiveModel =
+        invitation.into_active_model();
+    invitation.accepted_at = Set(Some(Utc::now().into()));
+    save_active_invitation(&db, invitation).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn decline_invitation(
+    State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>, Path(path): Path<InvitationPath>,
+) -> Result<StatusCode, AppError> {
+    let invitation = find_my_invitation(&db, &user, path.invitation_id).await?;
+
+    let mut invitation = invitation.into_active_model();
+    invitation.declined_at = Set(Some(Utc::now().into()));
+    save_active_invitation(&db, invitation).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
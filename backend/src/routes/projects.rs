@@ -0,0 +1,253 @@
+use super::{
+    get_tasks::{GetTasksQuery, ResponseDataTasks},
+    project_access::{Owner, ProjectAccess, Viewer},
+};
+use crate::{
+    database::{
+        projects::{self, Model as ProjectModel},
+        users::Model as UserModel,
+    },
+    queires::{
+        project_member_queries::find_member_roles,
+        project_queries::{
+            delete_project as delete_project_query, find_projects_by_user,
+            save_active_project,
+        },
+        task_queries::find_project_tasks,
+    },
+    utils::{app_error::AppError, project_role::ProjectRole},
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, FixedOffset};
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+/// Colors are stored as `#rrggbb` hex codes.
+fn validate_color(color: &str) -> Result<(), ValidationError> {
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+    match is_hex {
+        true => Ok(()),
+        false => Err(ValidationError::new("color")
+            .with_message("must be a hex color like #1e90ff".into())),
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RequestCreateProject {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "must have 1 to 64 characters"
+    ))]
+    pub name: String,
+    #[validate(custom(function = "validate_color"))]
+    pub color: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RequestUpdateProject {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "must have 1 to 64 characters"
+    ))]
+    pub name: Option<String>,
+    #[serde(
+        default,                                    // <- important for deserialization
+        skip_serializing_if = "Option::is_none",    // <- important for serialization
+        with = "::serde_with::rust::double_option",
+    )]
+    pub color: Option<Option<String>>,
+    pub archived: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct GetProjectsQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+#[derive(Serialize)]
+pub struct ResponseProject {
+    id: i32,
+    name: String,
+    color: Option<String>,
+    archived: bool,
+    is_inbox: bool,
+    created_at: DateTime<FixedOffset>,
+    /// The logged in user's role in the project.
+    role: ProjectRole,
+}
+
+#[derive(Serialize)]
+pub struct ResponseDataProjects {
+    pub data: Vec<ResponseProject>,
+}
+
+impl ResponseProject {
+    fn new(project: ProjectModel, role: ProjectRole) -> Self {
+        Self {
+            role,
+            id: project.id,
+            name: project.name,
+            color: project.color,
+            archived: project.archived,
+            is_inbox: project.is_inbox,
+            created_at: project.created_at,
+        }
+    }
+}
+
+pub async fn create_project(
+    State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>,
+    Json(request): Json<RequestCreateProject>,
+) -> Result<(StatusCode, Json<ResponseProject>), AppError> {
+    if let Err(errors) = request.validate() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, errors.to_string()));
+    }
+
+    let project = projects::ActiveModel {
+        user_id: Set(user.id),
+        name: Set(request.name),
+        color: Set(request.color),
+        ..Default::default()
+    };
+    let project = save_active_project(&db, project).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ResponseProject::new(project, ProjectRole::Owner)),
+    ))
+}
+
+pub async fn get_projects(
+    Query(query): Query<GetProjectsQuery>,
+    State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>,
+) -> Result<Json<ResponseDataProjects>, AppError> {
+    let roles = find_member_roles(&db, user.id).await?;
+    let projects = find_projects_by_user(&db, user.id, query.include_archived)
+        .await?
+        .into_iter()
+        .map(|project| {
+            let role = match project.user_id == user.id {
+                true => ProjectRole::Owner,
+                false => roles
+                    .get(&project.id)
+                    .copied()
+                    .unwrap_or(ProjectRole::Viewer),
+            };
+            ResponseProject::new(project, role)
+        })
+        .collect();
+
+    Ok(Json(ResponseDataProjects { data: projects }))
+}
+
+pub async fn get_project(
+    ProjectAccess {
+        resource: project,
+        role,
+        ..
+    }: ProjectAccess<ProjectModel, Viewer>,
+) -> Json<ResponseProject> {
+    Json(ResponseProject::new(project, role))
+}
+
+pub async fn update_project(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: project,
+        role,
+        ..
+    }: ProjectAccess<ProjectModel, Owner>,
+    Json(request): Json<RequestUpdateProject>,
+) -> Result<Json<ResponseProject>, AppError> {
+    if let Err(errors) = request.validate() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, errors.to_string()));
+    }
+    if let Some(Some(color)) = &request.color {
+        if let Err(error) = validate_color(color) {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                error.to_string(),
+            ));
+        }
+    }
+
+    // New tasks land in the inbox, so it has to stay around.
+    if project.is_inbox && request.archived == Some(true) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "the inbox cannot be archived",
+        ));
+    }
+
+    let mut project = project.into_active_model();
+
+    if let Some(name) = request.name {
+        project.name = Set(name);
+    }
+
+    if let Some(color) = request.color {
+        project.color = Set(color);
+    }
+
+    if let Some(archived) = request.archived {
+        project.archived = Set(archived);
+    }
+
+    let project = save_active_project(&db, project).await?;
+
+    Ok(Json(ResponseProject::new(project, role)))
+}
+
+/// Deletes the project, its tasks move to the inbox.
+pub async fn delete_project(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: project, ..
+    }: ProjectAccess<ProjectModel, Owner>,
+) -> Result<StatusCode, AppError> {
+    if project.is_inbox {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "the inbox cannot be deleted",
+        ));
+    }
+
+    delete_project_query(&db, project).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The project's tasks, with the same filters, sorting and pagination as
+/// `GET /tasks`.
+pub async fn get_project_tasks(
+    Query(query): Query<GetTasksQuery>, State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>,
+    ProjectAccess {
+        resource: project, ..
+    }: ProjectAccess<ProjectModel, Viewer>,
+) -> Result<(StatusCode, Json<ResponseDataTasks>), AppError> {
+    let sort = query.sort()?;
+    let (offset, limit) = query.page()?;
+    let filter = query.filter(&user)?;
+    let page =
+        find_project_tasks(&db, project.id, &filter, &sort, offset, limit)
+            .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ResponseDataTasks::from_page(&db, page, offset).await?),
+    ))
+}
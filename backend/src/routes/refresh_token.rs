@@ -0,0 +1,95 @@
+use crate::{
+    queires::{
+        refresh_token_queries::{
+            create_refresh_token, find_refresh_token, revoke_refresh_token,
+            revoke_token_family,
+        },
+        session_queries::{
+            delete_session, find_session_by_id, rotate_session_token,
+        },
+        user_queries::find_by_id,
+    },
+    utils::{
+        app_error::AppError,
+        auth_cookies::AuthCookies,
+        jwt::{create_token, JwtIssuer},
+    },
+};
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::Utc;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct RequestRefresh {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct ResponseRefresh {
+    token: String,
+    refresh_token: String,
+}
+
+pub async fn refresh(
+    cookies: AuthCookies, State(db): State<DatabaseConnection>,
+    State(jwt): State<JwtIssuer>, Json(request): Json<RequestRefresh>,
+) -> Result<Json<ResponseRefresh>, AppError> {
+    let stored = find_refresh_token(&db, &request.refresh_token).await?;
+
+    // A refresh token that was already rotated is being presented again, so
+    // either the client or an attacker holds a stolen copy. Kill the family
+    // and the session it belongs to.
+    if stored.revoked_at.is_some()
+        || !revoke_refresh_token(&db, stored.id).await?
+    {
+        eprintln!(
+            "Refresh token reuse detected for user {}, revoking family",
+            stored.user_id
+        );
+        revoke_token_family(&db, &stored.family_id).await?;
+
+        if let Some(session_id) = stored.session_id {
+            // The session may already be gone, which is what we want anyway.
+            let _ = delete_session(&db, session_id, stored.user_id).await;
+        }
+
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "refresh token has already been used, please login again",
+        ));
+    }
+
+    if stored.expires_at < Utc::now() {
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "refresh token expired, please login again",
+        ));
+    }
+
+    let session = match stored.session_id {
+        Some(session_id) => find_session_by_id(&db, session_id).await?,
+        None => None,
+    };
+    let Some(session) = session else {
+        revoke_token_family(&db, &stored.family_id).await?;
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "session has been revoked, please login again",
+        ));
+    };
+
+    let user = find_by_id(&db, stored.user_id).await?;
+    let (new_token, claims) = create_token(&jwt, &user)?;
+    let session = rotate_session_token(&db, session, &claims.jti).await?;
+    let refresh_token =
+        create_refresh_token(&db, user.id, session.id, Some(stored.family_id))
+            .await?;
+
+    cookies.start_session(&new_token);
+
+    Ok(Json(ResponseRefresh {
+        token: new_token,
+        refresh_token,
+    }))
+}
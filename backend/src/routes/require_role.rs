@@ -0,0 +1,56 @@
+use std::marker::PhantomData;
+
+use crate::{
+    database::users::Model as UserModel,
+    utils::{app_error::AppError, role::Role},
+};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+};
+
+/// Marker for the role a route requires, used as `RequireRole<Admin>`.
+pub trait RoleRequirement {
+    const ROLE: Role;
+}
+
+pub struct Admin;
+
+impl RoleRequirement for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// Rejects the request with 403 unless the logged in user has the role.
+/// The role is read from the user row loaded by `user_session`, so a
+/// demotion takes effect without waiting for the token to expire.
+pub struct RequireRole<R>(PhantomData<R>);
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    R: RoleRequirement,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts, _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let user = parts.extensions.get::<UserModel>().ok_or_else(|| {
+            AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "You are not authorized, please login or create account",
+            )
+        })?;
+
+        if Role::of(user) != R::ROLE {
+            return Err(AppError::new(
+                StatusCode::FORBIDDEN,
+                "You do not have permission to do this",
+            ));
+        }
+
+        Ok(RequireRole(PhantomData))
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{
+    queires::{
+        task_queries::restore_user_tasks,
+        user_queries::{find_deleted_by_id, save_active_user},
+    },
+    utils::app_error::AppError,
+};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+
+use super::{
+    require_role::{Admin, RequireRole},
+    users::ResponseUser,
+};
+
+/// Brings back a soft-deleted account and the tasks deleted along with it,
+/// as long as it has not been purged yet.
+pub async fn restore_user(
+    _admin: RequireRole<Admin>, Path(user_id): Path<i32>,
+    State(db): State<DatabaseConnection>,
+) -> Result<Json<ResponseUser>, AppError> {
+    let user = find_deleted_by_id(&db, user_id).await?;
+    let deleted_at = user.deleted_at;
+
+    let mut active_user = user.into_active_model();
+    active_user.deleted_at = Set(None);
+    let user = save_active_user(&db, active_user).await?;
+
+    if let Some(deleted_at) = deleted_at {
+        restore_user_tasks(&db, user.id, deleted_at).await?;
+    }
+
+    Ok(Json(user.into()))
+}
@@ -0,0 +1,60 @@
+use crate::{
+    database::{sessions::Model as SessionModel, users::Model as UserModel},
+    queires::session_queries::{find_sessions_by_user, revoke_session},
+    utils::{app_error::AppError, jwt::Claims},
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::{DateTime, FixedOffset};
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+
+use super::owned_resource::Owned;
+
+#[derive(Serialize)]
+pub struct ResponseSession {
+    id: i32,
+    user_agent: Option<String>,
+    ip: Option<String>,
+    created_at: DateTime<FixedOffset>,
+    last_seen_at: DateTime<FixedOffset>,
+    expires_at: DateTime<FixedOffset>,
+    current: bool,
+}
+
+#[derive(Serialize)]
+pub struct ResponseDataSessions {
+    pub data: Vec<ResponseSession>,
+}
+
+pub async fn get_my_sessions(
+    State(db): State<DatabaseConnection>,
+    Extension(user): Extension<UserModel>,
+    Extension(claims): Extension<Claims>,
+) -> Result<(StatusCode, Json<ResponseDataSessions>), AppError> {
+    let sessions = find_sessions_by_user(&db, user.id)
+        .await?
+        .into_iter()
+        .map(|session| ResponseSession {
+            current: session.jti == claims.jti,
+            id: session.id,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        })
+        .collect::<Vec<ResponseSession>>();
+
+    Ok((
+        StatusCode::OK,
+        Json(ResponseDataSessions { data: sessions }),
+    ))
+}
+
+pub async fn delete_my_session(
+    Owned(session): Owned<SessionModel>, State(db): State<DatabaseConnection>,
+) -> Result<StatusCode, AppError> {
+    revoke_session(&db, session).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
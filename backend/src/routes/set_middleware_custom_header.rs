@@ -10,9 +10,7 @@ pub async fn set_middleware_custom_header<B>(
     mut request: Request<B>, next: Next<B>,
 ) -> Result<Response, StatusCode> {
     let headers = request.headers();
-    let message = headers
-        .get("message")
-        .ok_or_else(|| StatusCode::BAD_REQUEST)?;
+    let message = headers.get("message").ok_or(StatusCode::BAD_REQUEST)?;
     let mut message = message
         .to_str()
         .map_err(|_error| StatusCode::BAD_REQUEST)?
@@ -0,0 +1,149 @@
+use super::project_access::{Editor, ProjectAccess, Viewer};
+use crate::{
+    database::{task_items, tasks::Model as TaskModel},
+    queires::task_item_queries::{
+        delete_task_item, find_task_item, find_task_items,
+        save_active_task_item,
+    },
+    utils::app_error::AppError,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct RequestCreateItem {
+    #[validate(length(min = 1, max = 255, message = "must not be empty"))]
+    pub title: String,
+    pub position: Option<i32>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RequestUpdateItem {
+    #[validate(length(min = 1, max = 255, message = "must not be empty"))]
+    pub title: Option<String>,
+    pub position: Option<i32>,
+    /// Stamps or clears `completed_at` with the server time.
+    pub completed: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct ResponseItem {
+    id: i32,
+    task_id: i32,
+    title: String,
+    position: i32,
+    completed_at: Option<DateTime<FixedOffset>>,
+}
+
+impl From<task_items::Model> for ResponseItem {
+    fn from(item: task_items::Model) -> Self {
+        Self {
+            id: item.id,
+            task_id: item.task_id,
+            title: item.title,
+            position: item.position,
+            completed_at: item.completed_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ItemPath {
+    pub item_id: i32,
+}
+
+fn validation_error(errors: validator::ValidationErrors) -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, errors.to_string())
+}
+
+pub async fn get_items(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess { resource: task, .. }: ProjectAccess<TaskModel, Viewer>,
+) -> Result<Json<Vec<ResponseItem>>, AppError> {
+    let items = find_task_items(&db, task.id)
+        .await?
+        .into_iter()
+        .map(ResponseItem::from)
+        .collect();
+
+    Ok(Json(items))
+}
+
+/// Adds an item to the task, at the end of the list unless a position is
+/// given.
+pub async fn create_item(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess { resource: task, .. }: ProjectAccess<TaskModel, Editor>,
+    Json(request): Json<RequestCreateItem>,
+) -> Result<(StatusCode, Json<ResponseItem>), AppError> {
+    request.validate().map_err(validation_error)?;
+
+    let position = match request.position {
+        Some(position) => position,
+        None => find_task_items(&db, task.id)
+            .await?
+            .last()
+            .map_or(0, |item| item.position + 1),
+    };
+
+    let item = task_items::ActiveModel {
+        task_id: Set(task.id),
+        title: Set(request.title),
+        position: Set(position),
+        ..Default::default()
+    };
+    let item = save_active_task_item(&db, item).await?;
+
+    Ok((StatusCode::CREATED, Json(item.into())))
+}
+
+pub async fn update_item(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess { resource: task, .. }: ProjectAccess<TaskModel, Editor>,
+    Path(path): Path<ItemPath>, Json(request): Json<RequestUpdateItem>,
+) -> Result<Json<ResponseItem>, AppError> {
+    request.validate().map_err(validation_error)?;
+
+    let item = find_task_item(&db, task.id, path.item_id).await?;
+    let was_completed = item.completed_at.is_some();
+    let mut item = item.into_active_model();
+
+    if let Some(title) = request.title {
+        item.title = Set(title);
+    }
+
+    if let Some(position) = request.position {
+        item.position = Set(position);
+    }
+
+    // Completing an item twice keeps the time it was first completed.
+    match request.completed {
+        Some(true) if !was_completed => {
+            item.completed_at = Set(Some(Utc::now().into()))
+        }
+        Some(false) => item.completed_at = Set(None),
+        _ => {}
+    }
+
+    let item = save_active_task_item(&db, item).await?;
+
+    Ok(Json(item.into()))
+}
+
+pub async fn delete_item(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess { resource: task, .. }: ProjectAccess<TaskModel, Editor>,
+    Path(path): Path<ItemPath>,
+) -> Result<StatusCode, AppError> {
+    let item = find_task_item(&db, task.id, path.item_id).await?;
+    delete_task_item(&db, item).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,43 @@
+use super::{
+    get_tasks::{task_response, ResponseTask},
+    project_access::{Editor, ProjectAccess},
+};
+use crate::{
+    database::tasks::Model as TaskModel,
+    queires::task_queries::{change_status, save_active_task},
+    utils::{app_error::AppError, task_status::TaskStatus},
+};
+use axum::{extract::State, http::StatusCode, Json};
+use sea_orm::{DatabaseConnection, IntoActiveModel};
+
+/// Marks the task as done, completed at the time of the request.
+pub async fn complete_task(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess { resource: task, .. }: ProjectAccess<TaskModel, Editor>,
+) -> Result<Json<ResponseTask>, AppError> {
+    let current = TaskStatus::of(&task);
+    let mut task = task.into_active_model();
+    change_status(&mut task, current, TaskStatus::Done)?;
+
+    let task = save_active_task(&db, task).await?;
+
+    Ok(Json(task_response(&db, task).await?))
+}
+
+/// Reopens a done task.
+pub async fn uncomplete_task(
+    State(db): State<DatabaseConnection>,
+    ProjectAccess { resource: task, .. }: ProjectAccess<TaskModel, Editor>,
+) -> Result<Json<ResponseTask>, AppError> {
+    let current = TaskStatus::of(&task);
+    if current != TaskStatus::Done {
+        return Err(AppError::new(StatusCode::CONFLICT, "task is not done"));
+    }
+
+    let mut task = task.into_active_model();
+    change_status(&mut task, current, TaskStatus::Todo)?;
+
+    let task = save_active_task(&db, task).await?;
+
+    Ok(Json(task_response(&db, task).await?))
+}
@@ -1,50 +1,98 @@
 /*
 ** Atomic Updates
 */
-use crate::database::{tasks, tasks::Entity as Tasks};
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
+use super::project_access::{AnyTask, Editor, ProjectAccess};
+use crate::{
+    database::{tasks, tasks::Entity as Tasks, users::Model as UserModel},
+    queires::{
+        task_item_queries::sync_task_items_deleted_at,
+        task_queries::change_status,
+    },
+    utils::{role::is_admin, task_status::TaskStatus},
 };
+use axum::{extract::State, http::StatusCode, Extension, Json};
 use sea_orm::{
     prelude::DateTimeWithTimeZone, ColumnTrait, DatabaseConnection,
     EntityTrait, QueryFilter, Set,
 };
 use serde::Deserialize;
-#[warn(dead_code)]
+
 #[derive(Deserialize)]
 pub struct RequestTask {
-    pub id: Option<i32>,
     pub priority: Option<String>,
     pub title: String,
-    pub completed_at: Option<DateTimeWithTimeZone>,
     pub description: Option<String>,
     pub deleted_at: Option<DateTimeWithTimeZone>,
-    pub user_id: Option<i32>,
     pub is_default: Option<bool>,
+    pub due_at: Option<DateTimeWithTimeZone>,
+    pub remind_at: Option<DateTimeWithTimeZone>,
+    /// Kept as is when missing, also stamps or clears `completed_at`, see
+    /// [`change_status`].
+    pub status: Option<TaskStatus>,
 }
 
 pub async fn atomic_update(
-    Path(task_id): Path<i32>, State(database): State<DatabaseConnection>,
+    ProjectAccess {
+        resource: AnyTask(task),
+        ..
+    }: ProjectAccess<AnyTask, Editor>,
+    Extension(user): Extension<UserModel>,
+    State(database): State<DatabaseConnection>,
     Json(request_task): Json<RequestTask>,
 ) -> Result<(), StatusCode> {
-    let update_task = tasks::ActiveModel {
+    // A trashed task is not found, except by admins restoring it.
+    let restoring = request_task.deleted_at.is_none();
+    if task.deleted_at.is_some() && !(restoring && is_admin(&user)) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let task_id = task.id;
+    let current_status = TaskStatus::of(&task);
+
+    // A new reminder time fires again even if the old one already did.
+    let reminded_at = match request_task.remind_at == task.remind_at {
+        true => task.reminded_at,
+        false => None,
+    };
+
+    // The owner and assignee are never taken from the body, a task cannot be
+    // handed over to another user through an update.
+    let mut update_task = tasks::ActiveModel {
         id: Set(task_id),
         priority: Set(request_task.priority),
         title: Set(request_task.title),
-        completed_at: Set(request_task.completed_at),
+        completed_at: Set(task.completed_at),
         description: Set(request_task.description),
         deleted_at: Set(request_task.deleted_at),
-        user_id: Set(request_task.user_id),
+        user_id: Set(task.user_id),
+        project_id: Set(task.project_id),
+        assignee_id: Set(task.assignee_id),
+        due_at: Set(request_task.due_at),
+        remind_at: Set(request_task.remind_at),
+        reminded_at: Set(reminded_at),
         is_default: Set(request_task.is_default),
+        status: Set(current_status.as_str().to_owned()),
     };
 
+    if let Some(status) = request_task.status {
+        change_status(&mut update_task, current_status, status)
+            .map_err(|_| StatusCode::CONFLICT)?;
+    }
+
     Tasks::update(update_task)
         .filter(tasks::Column::Id.eq(task_id))
         .exec(&database)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    sync_task_items_deleted_at(
+        &database,
+        task_id,
+        task.deleted_at,
+        request_task.deleted_at,
+    )
+    .await
+    .map_err(|error| error.code())?;
+
     Ok(())
 }
@@ -1,25 +1,51 @@
 use crate::{
-    database::users::{self, Entity as Users},
-    queires::user_queries::{find_by_username, save_active_user},
+    app_state::AppConfig,
+    database::users::{self, Entity as Users, Model as UserModel},
+    queires::{
+        one_time_token_queries::{create_one_time_token, TokenPurpose},
+        project_queries::create_inbox,
+        refresh_token_queries::create_refresh_token,
+        revoked_token_queries::revoke_token,
+        session_queries::{
+            create_session, find_session_by_jti, revoke_session,
+        },
+        user_queries::{save_active_user, try_find_by_username},
+    },
     utils::{
-        app_error::AppError, jwt::create_token, token_wrapper::TokenWrapper,
+        app_error::AppError,
+        auth_cookies::AuthCookies,
+        client_info::ClientInfo,
+        jwt::{create_token, Claims, JwtIssuer},
+        login_throttle::SharedLoginThrottle,
+        mailer::SharedMailer,
+        password_hasher::PasswordHasher,
+        role::Role,
     },
 };
 use axum::{
     async_trait,
     body::HttpBody,
-    extract::{FromRequest, Path, State},
+    extract::{FromRequest, Query, State},
     http::{Request, StatusCode},
     BoxError, Extension, Json, RequestExt,
 };
-use bcrypt::{hash, verify};
 use sea_orm::{
-    ActiveModelTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Set,
+    ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    Set,
 };
 use serde::{Deserialize, Serialize};
-use tower_cookies::{Cookie, Cookies};
 use validator::Validate;
 
+use super::{
+    login_lockouts::{
+        clear_account_lockout, ensure_not_locked, login_keys,
+        record_failed_login,
+    },
+    owned_resource::Owned,
+    require_role::{Admin, RequireRole},
+    verify_email::send_verification_email,
+};
+
 #[derive(Deserialize, Debug, Validate)]
 pub struct RequestUser {
     #[validate(email(message = "must be a valid email"))]
@@ -53,7 +79,7 @@ where
 
         if let Err(errors) = user.validate() {
             let field_errors = errors.field_errors();
-            for (_, error) in field_errors {
+            if let Some((_, error)) = field_errors.into_iter().next() {
                 return Err(AppError::new(
                     StatusCode::BAD_REQUEST,
                     error.first().unwrap().clone().message.unwrap().to_string(),
@@ -71,146 +97,238 @@ where
 pub struct ResponseUser {
     username: String,
     id: i32,
+    role: String,
+    verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    mfa_enabled: bool,
+    /// Set instead of `token` when the login still needs a second factor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mfa_token: Option<String>,
+}
+
+impl From<UserModel> for ResponseUser {
+    fn from(user: UserModel) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            verified: user.verified_at.is_some(),
+            token: None,
+            refresh_token: None,
+            mfa_enabled: user.totp_enabled_at.is_some(),
+            mfa_token: None,
+        }
+    }
+}
+
+/// Opens a new session for the device the request came from and returns
+/// the access token together with the refresh token bound to it.
+pub async fn start_session(
+    db: &DatabaseConnection, jwt: &JwtIssuer, user: &UserModel,
+    client: &ClientInfo,
+) -> Result<(String, String), AppError> {
+    let (token, claims) = create_token(jwt, user)?;
+    let session = create_session(db, user.id, &claims.jti, client).await?;
+    let refresh_token =
+        create_refresh_token(db, user.id, session.id, None).await?;
+
+    Ok((token, refresh_token))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_user(
-    State(db): State<DatabaseConnection>,
-    State(jwt_secret): State<TokenWrapper>, user: RequestUser,
+    cookies: AuthCookies, client: ClientInfo,
+    State(db): State<DatabaseConnection>, State(jwt): State<JwtIssuer>,
+    State(mailer): State<SharedMailer>, State(config): State<AppConfig>,
+    State(hasher): State<PasswordHasher>, user: RequestUser,
 ) -> Result<(StatusCode, Json<ResponseUser>), AppError> {
     let new_user = users::ActiveModel {
-        username: Set(user.username.clone()),
-        password: Set(hash_password(&user.password)?),
-        token: Set(Some(create_token(&jwt_secret.0, user.username)?)),
+        username: Set(user.username),
+        password: Set(hasher.hash(user.password).await?),
+        role: Set(Role::User.as_str().to_owned()),
         ..Default::default()
+    };
+    let new_user = save_active_user(&db, new_user).await?;
+    create_inbox(&db, new_user.id).await?;
+
+    // The account exists at this point, a failed email can be retried from
+    // the resend endpoint so it should not fail the signup.
+    if send_verification_email(&db, &mailer, &config, &new_user)
+        .await
+        .is_err()
+    {
+        eprintln!("Error sending verification to new user {}", new_user.id);
     }
-    .save(&db)
-    .await
-    .map_err(|error| {
-        let error_message = error.to_string();
-
-        if error_message
-            .contains("duplicate key value violates unique constraint \"users_username_key\"")
-        {
-            AppError::new(
-                StatusCode::BAD_REQUEST,
-                "Username already taken, try again with a different user name",
-            )
-        } else {
-            eprintln!("Error creating user: {:?}", error_message);
-            AppError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Something went wrong, please try again",
-            )
-        }
-    })?;
+
+    let (token, refresh_token) =
+        start_session(&db, &jwt, &new_user, &client).await?;
+    cookies.start_session(&token);
 
     Ok((
         StatusCode::CREATED,
         Json(ResponseUser {
-            username: new_user.username.unwrap(),
-            id: new_user.id.unwrap(),
-            token: new_user.token.unwrap(),
+            token: Some(token),
+            refresh_token: Some(refresh_token),
+            ..new_user.into()
         }),
     ))
 }
 
 pub async fn get_one_user(
-    Path(user_id): Path<i32>, State(db): State<DatabaseConnection>,
+    Owned(user): Owned<UserModel>,
 ) -> Result<Json<ResponseUser>, StatusCode> {
-    let user = Users::find_by_id(user_id).one(&db).await.unwrap();
-    if let Some(user) = user {
-        Ok(Json(ResponseUser {
-            id: user.id,
-            username: user.username,
-            token: user.token,
-        }))
-    } else {
-        Err(StatusCode::NOT_FOUND)
-    }
+    Ok(Json(user.into()))
+}
+
+#[derive(Deserialize)]
+pub struct GetUsersQuery {
+    /// Lists the soft-deleted accounts instead, e.g. to restore one.
+    #[serde(default)]
+    pub deleted: bool,
 }
 
 pub async fn get_all_users(
+    _admin: RequireRole<Admin>, Query(query): Query<GetUsersQuery>,
     State(db): State<DatabaseConnection>,
 ) -> Result<Json<Vec<ResponseUser>>, StatusCode> {
+    let deleted = match query.deleted {
+        true => users::Column::DeletedAt.is_not_null(),
+        false => users::Column::DeletedAt.is_null(),
+    };
+
     let users = Users::find()
+        .filter(deleted)
         .all(&db)
         .await
         .map_err(|_error| StatusCode::INTERNAL_SERVER_ERROR)?
         .into_iter()
-        .map(|db_user| ResponseUser {
-            id: db_user.id,
-            username: db_user.username,
-            token: db_user.token,
-        })
+        .map(ResponseUser::from)
         .collect();
 
     Ok(Json(users))
 }
 
 pub async fn login(
-    cookies: Cookies, State(db): State<DatabaseConnection>,
-    State(jwt_secret): State<TokenWrapper>,
+    cookies: AuthCookies, client: ClientInfo,
+    State(db): State<DatabaseConnection>, State(jwt): State<JwtIssuer>,
+    State(throttle): State<SharedLoginThrottle>,
+    State(hasher): State<PasswordHasher>,
     Json(request_user): Json<RequestUser>,
 ) -> Result<Json<ResponseUser>, AppError> {
-    let user = find_by_username(&db, request_user.username).await?;
+    let keys = login_keys(&request_user.username, &client);
+    ensure_not_locked(&throttle, &keys).await?;
 
-    if !verify_password(&request_user.password, &user.password)? {
+    // Only a wrong guess counts against the user, not the database failing.
+    let Some(user) = try_find_by_username(&db, &request_user.username).await?
+    else {
+        record_failed_login(&throttle, &keys).await?;
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "incorrect username and/or password",
+        ));
+    };
+
+    if !hasher
+        .verify(request_user.password.clone(), user.password.clone())
+        .await?
+    {
+        record_failed_login(&throttle, &keys).await?;
         return Err(AppError::new(
             StatusCode::UNAUTHORIZED,
             "incorrect username and/or password",
         ));
     }
 
-    let new_token = create_token(&jwt_secret.0, user.username.clone())?;
-    let mut user = user.into_active_model();
+    let user = if hasher.needs_rehash(&user.password) {
+        upgrade_password_hash(&db, &hasher, user, request_user.password).await
+    } else {
+        user
+    };
+
+    finish_first_factor(&cookies, &client, &db, &jwt, &throttle, user).await
+}
 
-    user.token = Set(Some(new_token.clone()));
+/// Called once the user proved who they are. Users with two-factor
+/// authentication get the token `/users/login/mfa` expects instead of a
+/// session.
+pub async fn finish_first_factor(
+    cookies: &AuthCookies, client: &ClientInfo, db: &DatabaseConnection,
+    jwt: &JwtIssuer, throttle: &SharedLoginThrottle, user: UserModel,
+) -> Result<Json<ResponseUser>, AppError> {
+    // The lockout is only cleared once the second factor is in too,
+    // otherwise every correct password would reset the code guessing.
+    if user.totp_enabled_at.is_some() {
+        let mfa_token =
+            create_one_time_token(db, user.id, TokenPurpose::MfaPending)
+                .await?;
 
-    let user = save_active_user(&db, user).await?;
+        return Ok(Json(ResponseUser {
+            mfa_token: Some(mfa_token),
+            ..user.into()
+        }));
+    }
 
-    cookies.add(Cookie::new("auth-token", new_token));
+    complete_login(cookies, client, db, jwt, throttle, user).await
+}
+
+/// Finishes a login once every factor checked out: lifts the account
+/// lockout and hands out a new session.
+pub async fn complete_login(
+    cookies: &AuthCookies, client: &ClientInfo, db: &DatabaseConnection,
+    jwt: &JwtIssuer, throttle: &SharedLoginThrottle, user: UserModel,
+) -> Result<Json<ResponseUser>, AppError> {
+    clear_account_lockout(throttle, &user.username).await?;
+
+    let (new_token, refresh_token) =
+        start_session(db, jwt, &user, client).await?;
+
+    cookies.start_session(&new_token);
 
     let response = ResponseUser {
-        id: user.id,
-        username: user.username,
-        token: user.token,
+        token: Some(new_token),
+        refresh_token: Some(refresh_token),
+        ..user.into()
     };
 
     Ok(Json(response))
 }
 
 pub async fn logout(
-    cookies: Cookies, Extension(user): Extension<users::Model>,
+    cookies: AuthCookies, Extension(claims): Extension<Claims>,
     State(db): State<DatabaseConnection>,
 ) -> Result<StatusCode, AppError> {
-    let mut user = user.into_active_model();
-
-    user.token = Set(None);
-
-    save_active_user(&db, user).await?;
+    match find_session_by_jti(&db, &claims.jti).await? {
+        Some(session) => revoke_session(&db, session).await?,
+        None => revoke_token(&db, &claims.jti, claims.expires_at()).await?,
+    }
 
-    cookies.remove(Cookie::named("auth-token"));
+    cookies.end_session();
 
     Ok(StatusCode::OK)
 }
 
-fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
-    verify(password, hash).map_err(|error| {
-        eprintln!("Error verifying password: {:?}", error);
-        AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "The was a problem verifying your password",
-        )
-    })
-}
+/// Replaces a hash made with an outdated algorithm or parameters now that
+/// the plain password is known. Failing only means trying again next login.
+async fn upgrade_password_hash(
+    db: &DatabaseConnection, hasher: &PasswordHasher, user: UserModel,
+    password: String,
+) -> UserModel {
+    let new_hash = match hasher.hash(password).await {
+        Ok(new_hash) => new_hash,
+        Err(_) => return user,
+    };
 
-fn hash_password(password: &str) -> Result<String, AppError> {
-    hash(password, 14).map_err(|error| {
-        eprintln!("Error hashing password: {:?}", error);
-        AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Error securing password",
-        )
-    })
+    let mut active_user = user.clone().into_active_model();
+    active_user.password = Set(new_hash);
+
+    match save_active_user(db, active_user).await {
+        Ok(updated) => updated,
+        Err(_) => {
+            eprintln!("Error upgrading password hash of user {}", user.id);
+            user
+        }
+    }
 }
@@ -17,7 +17,7 @@ pub async fn validate_json(
     Json(JsonResponse {
         username: user.username,
         password: user.password,
-        github: if user.github == None {
+        github: if user.github.is_none() {
             Some("https://github.com/FMFigueroa".to_owned())
         } else {
             user.github
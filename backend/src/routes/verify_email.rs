@@ -0,0 +1,80 @@
+use crate::{
+    app_state::AppConfig,
+    database::users::Model as UserModel,
+    queires::{
+        one_time_token_queries::{
+            consume_one_time_token, create_one_time_token, TokenPurpose,
+        },
+        user_queries::{find_by_id, save_active_user},
+    },
+    utils::{
+        app_error::AppError,
+        mailer::{Email, SharedMailer},
+    },
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, IntoActiveModel, Set};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct RequestVerify {
+    pub token: String,
+}
+
+/// Issues a fresh verification token for the user and emails it.
+pub async fn send_verification_email(
+    db: &DatabaseConnection, mailer: &SharedMailer, config: &AppConfig,
+    user: &UserModel,
+) -> Result<(), AppError> {
+    let token =
+        create_one_time_token(db, user.id, TokenPurpose::VerifyEmail).await?;
+
+    let email = Email {
+        to: user.username.clone(),
+        subject: "Confirm your email address".to_owned(),
+        body: format!(
+            "Welcome! Confirm your email address by opening the link below. \
+             It expires in 24 hours.\n\n{}/verify?token={}\n",
+            config.app_url, token
+        ),
+    };
+
+    mailer.0.send(email).await.map_err(|error| {
+        eprintln!("Error sending verification email: {:?}", error);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not send the verification email, please try again later",
+        )
+    })
+}
+
+pub async fn verify_email(
+    State(db): State<DatabaseConnection>, Json(request): Json<RequestVerify>,
+) -> Result<StatusCode, AppError> {
+    let token =
+        consume_one_time_token(&db, &request.token, TokenPurpose::VerifyEmail)
+            .await?;
+
+    let mut user = find_by_id(&db, token.user_id).await?.into_active_model();
+    user.verified_at = Set(Some(Utc::now().into()));
+    save_active_user(&db, user).await?;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn resend_verification(
+    State(db): State<DatabaseConnection>, State(mailer): State<SharedMailer>,
+    State(config): State<AppConfig>, Extension(user): Extension<UserModel>,
+) -> Result<StatusCode, AppError> {
+    if user.verified_at.is_some() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "your email is already verified",
+        ));
+    }
+
+    send_verification_email(&db, &mailer, &config, &user).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
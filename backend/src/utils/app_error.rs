@@ -1,13 +1,15 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug)]
 pub struct AppError {
     code: StatusCode,
     message: String,
+    retry_after: Option<u64>,
 }
 
 impl AppError {
@@ -15,21 +17,38 @@ impl AppError {
         Self {
             code,
             message: message.into(),
+            retry_after: None,
         }
     }
+
+    pub fn code(&self) -> StatusCode {
+        self.code
+    }
+
+    /// Adds a `Retry-After` header, in seconds, to the response.
+    pub fn with_retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
 }
 
 // Trait IntoResponse
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         //Instancia de into_response
-        (
+        let mut response = (
             self.code,
             Json(ErrorResponse {
                 error: self.message.clone(),
             }),
         )
-            .into_response()
+            .into_response();
+
+        if let Some(seconds) = self.retry_after {
+            response.headers_mut().insert(RETRY_AFTER, seconds.into());
+        }
+
+        response
     }
 }
 
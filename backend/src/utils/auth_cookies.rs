@@ -0,0 +1,167 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, HeaderMap, Method, StatusCode},
+};
+use eyre::Result;
+use tower_cookies::{cookie::SameSite, Cookie, Cookies};
+
+use super::{
+    app_error::AppError,
+    secure_token::{generate_token, tokens_match},
+};
+use crate::app_state::AppConfig;
+
+pub const AUTH_COOKIE: &str = "auth-token";
+/// Readable by the frontend, which echoes it in [`CSRF_HEADER`] so a cross
+/// site form riding on the auth cookie cannot pass.
+pub const CSRF_COOKIE: &str = "csrf-token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Attributes shared by the cookies that carry a browser session.
+#[derive(Clone, Debug)]
+pub struct AuthCookieConfig {
+    pub domain: Option<String>,
+    pub path: String,
+    pub secure: bool,
+    pub same_site: SameSite,
+}
+
+impl Default for AuthCookieConfig {
+    fn default() -> Self {
+        Self {
+            domain: None,
+            path: "/".to_owned(),
+            secure: true,
+            same_site: SameSite::Lax,
+        }
+    }
+}
+
+/// The request's cookies, with the configuration to write session cookies.
+pub struct AuthCookies {
+    cookies: Cookies,
+    config: AuthCookieConfig,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthCookies
+where
+    AppConfig: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts, state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, state).await.map_err(
+            |error| {
+                eprintln!("Error extracting cookies: {:?}", error);
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error",
+                )
+            },
+        )?;
+
+        Ok(AuthCookies {
+            cookies,
+            config: AppConfig::from_ref(state).auth_cookie,
+        })
+    }
+}
+
+impl AuthCookies {
+    fn build(&self, name: &'static str, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::build(name, value)
+            .path(self.config.path.clone())
+            .secure(self.config.secure)
+            .same_site(self.config.same_site)
+            .http_only(name == AUTH_COOKIE)
+            .finish();
+
+        if let Some(domain) = &self.config.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        cookie
+    }
+
+    /// The underlying jar, for cookies that are not about the session.
+    pub fn jar(&self) -> &Cookies {
+        &self.cookies
+    }
+
+    /// Stores the access token along with a fresh CSRF token.
+    pub fn start_session(&self, token: &str) {
+        self.cookies.add(self.build(AUTH_COOKIE, token.to_owned()));
+        self.cookies.add(self.build(CSRF_COOKIE, generate_token()));
+    }
+
+    pub fn end_session(&self) {
+        self.cookies.remove(self.build(AUTH_COOKIE, String::new()));
+        self.cookies.remove(self.build(CSRF_COOKIE, String::new()));
+    }
+
+    pub fn auth_token(&self) -> Option<String> {
+        self.cookies
+            .get(AUTH_COOKIE)
+            .map(|cookie| cookie.value().to_owned())
+    }
+
+    /// Double-submit check for requests authenticated by cookie: anything
+    /// that can change state has to repeat the CSRF cookie in a header.
+    pub fn verify_csrf(
+        &self, method: &Method, headers: &HeaderMap,
+    ) -> Result<(), AppError> {
+        if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+            return Ok(());
+        }
+
+        let cookie = self.cookies.get(CSRF_COOKIE);
+        let header = headers
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        match (cookie, header) {
+            (Some(cookie), Some(header))
+                if tokens_match(cookie.value(), header) =>
+            {
+                Ok(())
+            }
+            _ => Err(AppError::new(
+                StatusCode::FORBIDDEN,
+                "missing or invalid csrf token",
+            )),
+        }
+    }
+}
+
+/// Reads `AUTH_COOKIE_DOMAIN`, `AUTH_COOKIE_PATH`, `AUTH_COOKIE_SECURE` and
+/// `AUTH_COOKIE_SAME_SITE` (strict, lax or none).
+pub fn auth_cookie_config_from_env() -> Result<AuthCookieConfig> {
+    let mut config = AuthCookieConfig::default();
+
+    if let Ok(domain) = dotenvy::var("AUTH_COOKIE_DOMAIN") {
+        config.domain = Some(domain).filter(|domain| !domain.is_empty());
+    }
+    if let Ok(path) = dotenvy::var("AUTH_COOKIE_PATH") {
+        config.path = path;
+    }
+    if let Ok(secure) = dotenvy::var("AUTH_COOKIE_SECURE") {
+        config.secure = secure.parse().map_err(|_| {
+            eyre::eyre!("Invalid AUTH_COOKIE_SECURE {secure:?}")
+        })?;
+    }
+    if let Ok(same_site) = dotenvy::var("AUTH_COOKIE_SAME_SITE") {
+        config.same_site = match same_site.to_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            other => eyre::bail!("Unknown AUTH_COOKIE_SAME_SITE {other:?}"),
+        };
+    }
+
+    Ok(config)
+}
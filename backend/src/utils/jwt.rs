@@ -1,51 +1,136 @@
+use std::sync::Arc;
+
 use axum::http::StatusCode;
-use chrono::{Duration, Utc};
-use dotenvy::dotenv;
-use jsonwebtoken::{
-    decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
-};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
 use serde::{Deserialize, Serialize};
 
-use super::app_error::AppError;
+use super::{
+    app_error::AppError, key_ring::KeyRing, role::Role,
+    secure_token::generate_token,
+};
+use crate::{
+    database::users::Model as UserModel, queires::session_queries::SESSION_DAYS,
+};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Claims {
-    exp: usize,
-    username: String,
+    pub iss: String,
+    pub aud: String,
+    pub sub: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+    pub jti: String,
+    pub username: String,
+    pub role: Role,
 }
 
-pub fn create_token(
-    secret: &str, username: String,
-) -> Result<String, AppError> {
-    dotenv().ok();
+/// What goes into access tokens besides the user.
+#[derive(Clone, Debug)]
+pub struct JwtConfig {
+    pub issuer: String,
+    pub audience: String,
+    pub ttl: Duration,
+    /// Clock skew tolerated on `exp` and `nbf`, in seconds.
+    pub leeway: u64,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            issuer: "web_app".to_owned(),
+            audience: "web_app".to_owned(),
+            ttl: Duration::try_hours(1).expect("Failed to create duration"),
+            leeway: 60,
+        }
+    }
+}
 
+/// Issues and validates access tokens with the key ring.
+#[derive(Clone)]
+pub struct JwtIssuer {
+    pub keys: Arc<KeyRing>,
+    pub config: JwtConfig,
+}
+
+impl Claims {
+    pub fn user_id(&self) -> Result<i32, AppError> {
+        self.sub.parse().map_err(|_error| {
+            AppError::new(StatusCode::UNAUTHORIZED, "not authenticated!")
+        })
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.exp as i64, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+pub fn create_token(
+    jwt: &JwtIssuer, user: &UserModel,
+) -> Result<(String, Claims), AppError> {
     let now = Utc::now();
-    let expires_at =
-        now + Duration::try_hours(1).expect("Failed to create duration");
-    let exp = expires_at.timestamp() as usize;
-    let claims = Claims { exp, username };
-    let token_header = Header::default();
-    let key = EncodingKey::from_secret(secret.as_bytes());
-
-    encode(&token_header, &claims, &key).map_err(|error| {
-        eprintln!("Error creating token: {:?}", error);
-        AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "There was an error, please try again later",
-        )
-    })
-}
-
-pub fn validate_token(secret: &str, token: &str) -> Result<bool, AppError> {
-    let key = DecodingKey::from_secret(secret.as_bytes());
-    let validation = Validation::new(Algorithm::HS256);
-    decode::<Claims>(token, &key, &validation)
+    let issued_at = now.timestamp() as usize;
+    let claims = Claims {
+        iss: jwt.config.issuer.clone(),
+        aud: jwt.config.audience.clone(),
+        sub: user.id.to_string(),
+        iat: issued_at,
+        nbf: issued_at,
+        exp: (now + jwt.config.ttl).timestamp() as usize,
+        jti: generate_token(),
+        username: user.username.clone(),
+        role: Role::of(user),
+    };
+    let key = jwt.keys.signing_key();
+    let mut token_header = Header::new(key.algorithm);
+    token_header.kid = Some(key.kid.clone());
+
+    let token =
+        encode(&token_header, &claims, &key.encoding).map_err(|error| {
+            eprintln!("Error creating token: {:?}", error);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })?;
+
+    Ok((token, claims))
+}
+
+pub fn validate_token(
+    jwt: &JwtIssuer, token: &str,
+) -> Result<Claims, AppError> {
+    let not_authenticated =
+        || AppError::new(StatusCode::UNAUTHORIZED, "not authenticated!");
+
+    let header = decode_header(token).map_err(|_error| not_authenticated())?;
+    // The key decides the algorithm, never the token itself.
+    let key = jwt
+        .keys
+        .find(header.kid.as_deref())
+        .ok_or_else(not_authenticated)?;
+
+    let mut validation = Validation::new(key.algorithm);
+    validation.set_issuer(&[&jwt.config.issuer]);
+    validation.set_audience(&[&jwt.config.audience]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+    validation.leeway = jwt.config.leeway;
+
+    decode::<Claims>(token, &key.decoding, &validation)
         .map_err(|error| match error.kind() {
             jsonwebtoken::errors::ErrorKind::InvalidToken
             | jsonwebtoken::errors::ErrorKind::InvalidSignature
-            | jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-                AppError::new(StatusCode::UNAUTHORIZED, "not authenticated!")
-            }
+            | jsonwebtoken::errors::ErrorKind::ExpiredSignature
+            | jsonwebtoken::errors::ErrorKind::ImmatureSignature
+            | jsonwebtoken::errors::ErrorKind::InvalidIssuer
+            | jsonwebtoken::errors::ErrorKind::InvalidAudience
+            | jsonwebtoken::errors::ErrorKind::InvalidSubject
+            | jsonwebtoken::errors::ErrorKind::MissingRequiredClaim(_)
+            | jsonwebtoken::errors::ErrorKind::Json(_)
+            | jsonwebtoken::errors::ErrorKind::Base64(_)
+            | jsonwebtoken::errors::ErrorKind::Utf8(_) => not_authenticated(),
             _ => {
                 eprintln!("Error validating token: {:?}", error);
                 AppError::new(
@@ -54,5 +139,41 @@ pub fn validate_token(secret: &str, token: &str) -> Result<bool, AppError> {
                 )
             }
         })
-        .map(|_claim| true)
+        .map(|token_data| token_data.claims)
+}
+
+fn env_seconds(name: &str) -> eyre::Result<Option<i64>> {
+    match dotenvy::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| eyre::eyre!("Invalid {name} {value:?}")),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Reads `JWT_ISSUER`, `JWT_AUDIENCE`, `JWT_TTL_SECONDS` and
+/// `JWT_LEEWAY_SECONDS`, falling back to the defaults for any not set. The
+/// TTL is capped at the session lifetime, which revoked tokens rely on.
+pub fn jwt_config_from_env() -> eyre::Result<JwtConfig> {
+    let mut config = JwtConfig::default();
+
+    if let Ok(issuer) = dotenvy::var("JWT_ISSUER") {
+        config.issuer = issuer;
+    }
+    if let Ok(audience) = dotenvy::var("JWT_AUDIENCE") {
+        config.audience = audience;
+    }
+    if let Some(ttl) = env_seconds("JWT_TTL_SECONDS")? {
+        let session = Duration::try_days(SESSION_DAYS)
+            .expect("Failed to create duration");
+        config.ttl = Duration::try_seconds(ttl)
+            .ok_or_else(|| eyre::eyre!("Invalid JWT_TTL_SECONDS {ttl}"))?
+            .min(session);
+    }
+    if let Some(leeway) = env_seconds("JWT_LEEWAY_SECONDS")? {
+        config.leeway = leeway.max(0) as u64;
+    }
+
+    Ok(config)
 }
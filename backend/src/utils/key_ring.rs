@@ -0,0 +1,224 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use eyre::Result;
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet,
+        KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+        RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use rsa::{pkcs1::DecodeRsaPrivateKey, traits::PublicKeyParts, RsaPrivateKey};
+
+/// Key id given to the HS256 key built from `JWT_SECRET`.
+const SECRET_KID: &str = "default";
+
+/// One key tokens can be signed and verified with, identified by the `kid`
+/// header of the tokens it signed.
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub encoding: EncodingKey,
+    pub decoding: DecodingKey,
+    /// The public half, published so other services can verify tokens.
+    /// Shared secrets have none.
+    pub jwk: Option<Jwk>,
+}
+
+fn public_jwk(
+    kid: &str, algorithm: KeyAlgorithm, parameters: AlgorithmParameters,
+) -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(algorithm),
+            key_id: Some(kid.to_owned()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    }
+}
+
+impl SigningKey {
+    pub fn hmac(kid: &str, secret: &str) -> Self {
+        Self {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::HS256,
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        }
+    }
+
+    /// An RS256 key from a PKCS#1 or PKCS#8 PEM private key.
+    pub fn rsa_pem(kid: &str, pem: &str) -> Result<Self> {
+        let private_key = RsaPrivateKey::from_pkcs1_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs8_pem(pem))?;
+        let n = URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be());
+        let e = URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be());
+
+        Ok(Self {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::RS256,
+            encoding: EncodingKey::from_rsa_pem(pem.as_bytes())?,
+            decoding: DecodingKey::from_rsa_components(&n, &e)?,
+            jwk: Some(public_jwk(
+                kid,
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n,
+                    e,
+                }),
+            )),
+        })
+    }
+
+    /// An EdDSA key from a PKCS#8 PEM Ed25519 private key.
+    pub fn ed25519_pem(kid: &str, pem: &str) -> Result<Self> {
+        let private_key = ed25519_dalek::SigningKey::from_pkcs8_pem(pem)
+            .map_err(|error| eyre::eyre!("Invalid Ed25519 key: {error}"))?;
+        let x = URL_SAFE_NO_PAD.encode(private_key.verifying_key().as_bytes());
+
+        Ok(Self {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::EdDSA,
+            encoding: EncodingKey::from_ed_pem(pem.as_bytes())?,
+            decoding: DecodingKey::from_ed_components(&x)?,
+            jwk: Some(public_jwk(
+                kid,
+                KeyAlgorithm::EdDSA,
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x,
+                }),
+            )),
+        })
+    }
+
+    /// Picks the algorithm from the kind of key in the PEM.
+    pub fn from_pem(kid: &str, pem: &str) -> Result<Self> {
+        Self::rsa_pem(kid, pem)
+            .or_else(|_| Self::ed25519_pem(kid, pem))
+            .map_err(|_| {
+                eyre::eyre!("Key {kid:?} is not an RSA or Ed25519 key")
+            })
+    }
+}
+
+/// Every key tokens are accepted from, one of which signs new tokens.
+///
+/// Keys are rotated in steps, each one a rolling restart: add the new key so
+/// it is published in the JWKS, make it the active key once other services
+/// picked it up, then drop the old one after the tokens it signed expired.
+pub struct KeyRing {
+    active: usize,
+    keys: Vec<SigningKey>,
+}
+
+impl KeyRing {
+    pub fn new(keys: Vec<SigningKey>, active_kid: &str) -> Result<Self> {
+        let active = keys
+            .iter()
+            .position(|key| key.kid == active_kid)
+            .ok_or_else(|| eyre::eyre!("Unknown active key {active_kid:?}"))?;
+
+        Ok(Self { active, keys })
+    }
+
+    /// The key new tokens are signed with.
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.keys[self.active]
+    }
+
+    /// The key a token names in its header. Tokens without a `kid` predate
+    /// the key ring and can only have been signed by the active key.
+    pub fn find(&self, kid: Option<&str>) -> Option<&SigningKey> {
+        match kid {
+            Some(kid) => self.keys.iter().find(|key| key.kid == kid),
+            None => Some(self.signing_key()),
+        }
+    }
+
+    /// The public keys, as served from `/.well-known/jwks.json`.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+/// Loads the keys listed in `JWT_KEYS` as `kid=path/to/key.pem` pairs,
+/// signing with `JWT_ACTIVE_KID` or the first one. `JWT_SECRET` is still
+/// accepted for verification, and signs when no other keys are configured.
+pub fn key_ring_from_env() -> Result<KeyRing> {
+    let mut keys = vec![];
+
+    for entry in dotenvy::var("JWT_KEYS").unwrap_or_default().split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let (kid, path) = entry
+            .split_once('=')
+            .ok_or_else(|| eyre::eyre!("Invalid JWT_KEYS entry {entry:?}"))?;
+        let pem = std::fs::read_to_string(path)?;
+        keys.push(SigningKey::from_pem(kid, &pem)?);
+    }
+
+    if let Ok(secret) = dotenvy::var("JWT_SECRET") {
+        keys.push(SigningKey::hmac(SECRET_KID, &secret));
+    }
+
+    let active_kid = match dotenvy::var("JWT_ACTIVE_KID") {
+        Ok(kid) => kid,
+        Err(_) => keys.first().map(|key| key.kid.clone()).ok_or_else(|| {
+            eyre::eyre!("Either JWT_KEYS or JWT_SECRET is required")
+        })?,
+    };
+
+    KeyRing::new(keys, &active_kid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_ring() -> KeyRing {
+        KeyRing::new(
+            vec![SigningKey::hmac("old", "a"), SigningKey::hmac("new", "b")],
+            "new",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tokens_are_verified_with_the_key_they_name() {
+        let keys = key_ring();
+
+        assert_eq!(keys.find(Some("old")).unwrap().kid, "old");
+        assert_eq!(keys.find(Some("new")).unwrap().kid, "new");
+        assert!(keys.find(Some("gone")).is_none());
+    }
+
+    #[test]
+    fn tokens_without_a_kid_use_the_active_key() {
+        let keys = key_ring();
+
+        assert_eq!(keys.signing_key().kid, "new");
+        assert_eq!(keys.find(None).unwrap().kid, "new");
+    }
+
+    #[test]
+    fn the_active_key_must_exist() {
+        assert!(KeyRing::new(vec![SigningKey::hmac("a", "a")], "b").is_err());
+    }
+
+    #[test]
+    fn shared_secrets_are_not_published() {
+        assert!(key_ring().jwks().keys.is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use eyre::Result;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+
+use crate::database::login_attempts::{self, Entity as LoginAttempts};
+
+/// How many failures a key gets for free and how hard it is locked out after.
+pub struct ThrottlePolicy {
+    pub free_attempts: i32,
+    pub base_lockout_seconds: i64,
+    pub max_lockout_seconds: i64,
+    /// Failures older than this are forgotten.
+    pub reset_after_seconds: i64,
+}
+
+pub const ACCOUNT_POLICY: ThrottlePolicy = ThrottlePolicy {
+    free_attempts: 5,
+    base_lockout_seconds: 30,
+    max_lockout_seconds: 15 * 60,
+    reset_after_seconds: 60 * 60,
+};
+
+// IPs are shared behind NATs and proxies, so they get more room.
+pub const IP_POLICY: ThrottlePolicy = ThrottlePolicy {
+    free_attempts: 20,
+    base_lockout_seconds: 30,
+    max_lockout_seconds: 60 * 60,
+    reset_after_seconds: 60 * 60,
+};
+
+pub fn account_key(username: &str) -> String {
+    format!("user:{}", username.to_lowercase())
+}
+
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{ip}")
+}
+
+/// When a key with that many failures in a row is locked until, using
+/// exponential backoff once the free attempts are used up.
+fn lockout(
+    failures: i32, policy: &ThrottlePolicy, now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let over = failures - policy.free_attempts;
+
+    (over > 0).then(|| {
+        let seconds = policy
+            .base_lockout_seconds
+            .saturating_mul(1 << (over - 1).min(20))
+            .min(policy.max_lockout_seconds);
+        now + Duration::seconds(seconds)
+    })
+}
+
+/// Rows untouched for this long no longer count towards any policy.
+fn forget_after() -> Duration {
+    Duration::seconds(
+        ACCOUNT_POLICY
+            .reset_after_seconds
+            .max(IP_POLICY.reset_after_seconds),
+    )
+}
+
+#[derive(Clone, Debug)]
+struct Attempts {
+    failures: i32,
+    locked_until: Option<DateTime<Utc>>,
+    last_failed_at: DateTime<Utc>,
+}
+
+impl Attempts {
+    /// Counts one more failure, locking the key once the free attempts are
+    /// used up.
+    fn fail(previous: Option<Attempts>, policy: &ThrottlePolicy) -> Attempts {
+        let now = Utc::now();
+        let failures = match previous {
+            Some(previous)
+                if now - previous.last_failed_at
+                    < Duration::seconds(policy.reset_after_seconds) =>
+            {
+                previous.failures + 1
+            }
+            _ => 1,
+        };
+
+        Attempts {
+            failures,
+            locked_until: lockout(failures, policy, now),
+            last_failed_at: now,
+        }
+    }
+
+    fn retry_after(&self) -> Option<u64> {
+        let seconds = (self.locked_until? - Utc::now()).num_seconds();
+        (seconds > 0).then_some(seconds as u64)
+    }
+}
+
+/// Tracks failed logins per key (account or IP address).
+#[async_trait]
+pub trait LoginThrottle: Send + Sync {
+    /// Seconds until the key may try again, `None` when it is not locked.
+    async fn locked_for(&self, key: &str) -> Result<Option<u64>>;
+    async fn record_failure(
+        &self, key: &str, policy: &ThrottlePolicy,
+    ) -> Result<()>;
+    async fn clear(&self, key: &str) -> Result<()>;
+    /// Forgets keys that are neither locked nor recently failed, so keys for
+    /// made up usernames do not pile up. Returns how many were removed.
+    async fn sweep(&self) -> Result<u64>;
+}
+
+#[derive(Clone)]
+pub struct SharedLoginThrottle(pub Arc<dyn LoginThrottle>);
+
+/// Keeps attempts in process memory. Only suitable for a single instance.
+#[derive(Default)]
+pub struct MemoryLoginThrottle {
+    attempts: Mutex<HashMap<String, Attempts>>,
+}
+
+#[async_trait]
+impl LoginThrottle for MemoryLoginThrottle {
+    async fn locked_for(&self, key: &str) -> Result<Option<u64>> {
+        let attempts = self.attempts.lock().expect("throttle lock poisoned");
+
+        Ok(attempts.get(key).and_then(Attempts::retry_after))
+    }
+
+    async fn record_failure(
+        &self, key: &str, policy: &ThrottlePolicy,
+    ) -> Result<()> {
+        let mut attempts =
+            self.attempts.lock().expect("throttle lock poisoned");
+        let next = Attempts::fail(attempts.get(key).cloned(), policy);
+        attempts.insert(key.to_owned(), next);
+
+        Ok(())
+    }
+
+    async fn clear(&self, key: &str) -> Result<()> {
+        self.attempts
+            .lock()
+            .expect("throttle lock poisoned")
+            .remove(key);
+
+        Ok(())
+    }
+
+    async fn sweep(&self) -> Result<u64> {
+        let now = Utc::now();
+        let mut attempts =
+            self.attempts.lock().expect("throttle lock poisoned");
+        let before = attempts.len();
+        attempts.retain(|_, attempts| {
+            attempts.retry_after().is_some()
+                || now - attempts.last_failed_at < forget_after()
+        });
+
+        Ok((before - attempts.len()) as u64)
+    }
+}
+
+/// Keeps attempts in the `login_attempts` table so every instance behind a
+/// load balancer sees the same counters.
+pub struct DatabaseLoginThrottle {
+    db: DatabaseConnection,
+}
+
+impl DatabaseLoginThrottle {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    async fn find(&self, key: &str) -> Result<Option<Attempts>> {
+        let row = LoginAttempts::find_by_id(key.to_owned())
+            .one(&self.db)
+            .await?;
+
+        Ok(row.map(|row| Attempts {
+            failures: row.failures,
+            locked_until: row.locked_until.map(Into::into),
+            last_failed_at: row.last_failed_at.into(),
+        }))
+    }
+}
+
+#[async_trait]
+impl LoginThrottle for DatabaseLoginThrottle {
+    async fn locked_for(&self, key: &str) -> Result<Option<u64>> {
+        Ok(self.find(key).await?.and_then(|row| row.retry_after()))
+    }
+
+    /// Counts the failure in a single upsert so concurrent attempts, also
+    /// from other instances, never overwrite each other's count.
+    async fn record_failure(
+        &self, key: &str, policy: &ThrottlePolicy,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let reset_before: DateTimeWithTimeZone =
+            (now - Duration::seconds(policy.reset_after_seconds)).into();
+        let column = |column| Expr::col((LoginAttempts, column));
+
+        let row = LoginAttempts::insert(login_attempts::ActiveModel {
+            key: Set(key.to_owned()),
+            failures: Set(1),
+            locked_until: Set(None),
+            last_failed_at: Set(now.into()),
+        })
+        .on_conflict(
+            OnConflict::column(login_attempts::Column::Key)
+                .value(
+                    login_attempts::Column::Failures,
+                    Expr::case(
+                        column(login_attempts::Column::LastFailedAt)
+                            .gte(reset_before),
+                        column(login_attempts::Column::Failures).add(1),
+                    )
+                    .finally(1),
+                )
+                .update_column(login_attempts::Column::LastFailedAt)
+                .to_owned(),
+        )
+        .exec_with_returning(&self.db)
+        .await?;
+
+        // Only the attempt that produced the current count sets the lock, a
+        // slower one finishing later cannot shorten it.
+        let locked_until =
+            lockout(row.failures, policy, now).map(DateTimeWithTimeZone::from);
+        LoginAttempts::update_many()
+            .col_expr(
+                login_attempts::Column::LockedUntil,
+                Expr::value(locked_until),
+            )
+            .filter(login_attempts::Column::Key.eq(key))
+            .filter(login_attempts::Column::Failures.eq(row.failures))
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn clear(&self, key: &str) -> Result<()> {
+        LoginAttempts::delete_by_id(key.to_owned())
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn sweep(&self) -> Result<u64> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+
+        let result = LoginAttempts::delete_many()
+            .filter(
+                login_attempts::Column::LastFailedAt.lt(now - forget_after()),
+            )
+            .filter(
+                Condition::any()
+                    .add(login_attempts::Column::LockedUntil.is_null())
+                    .add(login_attempts::Column::LockedUntil.lt(now)),
+            )
+            .exec(&self.db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+}
+
+/// Builds the throttle selected by `LOGIN_THROTTLE` (`database` or
+/// `memory`, defaults to `database`).
+pub fn login_throttle_from_env(
+    db: &DatabaseConnection,
+) -> Result<SharedLoginThrottle> {
+    let kind = dotenvy::var("LOGIN_THROTTLE")
+        .unwrap_or_else(|_| "database".to_owned());
+
+    let throttle: Arc<dyn LoginThrottle> = match kind.as_str() {
+        "database" => Arc::new(DatabaseLoginThrottle::new(db.clone())),
+        "memory" => Arc::new(MemoryLoginThrottle::default()),
+        other => eyre::bail!("Unknown LOGIN_THROTTLE {other:?}"),
+    };
+
+    Ok(SharedLoginThrottle(throttle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_attempts_are_not_locked() {
+        let now = Utc::now();
+
+        for failures in 0..=ACCOUNT_POLICY.free_attempts {
+            assert_eq!(lockout(failures, &ACCOUNT_POLICY, now), None);
+        }
+    }
+
+    #[test]
+    fn lockouts_double_up_to_the_max() {
+        let now = Utc::now();
+        let seconds = |failures| {
+            (lockout(failures, &ACCOUNT_POLICY, now).unwrap() - now)
+                .num_seconds()
+        };
+
+        assert_eq!(seconds(6), 30);
+        assert_eq!(seconds(7), 60);
+        assert_eq!(seconds(8), 120);
+        assert_eq!(seconds(12), ACCOUNT_POLICY.max_lockout_seconds);
+        assert_eq!(seconds(i32::MAX), ACCOUNT_POLICY.max_lockout_seconds);
+    }
+
+    #[tokio::test]
+    async fn memory_throttle_locks_after_the_free_attempts() {
+        let throttle = MemoryLoginThrottle::default();
+
+        for _ in 0..ACCOUNT_POLICY.free_attempts {
+            throttle
+                .record_failure("user:a", &ACCOUNT_POLICY)
+                .await
+                .unwrap();
+        }
+        assert_eq!(throttle.locked_for("user:a").await.unwrap(), None);
+
+        throttle
+            .record_failure("user:a", &ACCOUNT_POLICY)
+            .await
+            .unwrap();
+        assert!(throttle.locked_for("user:a").await.unwrap().is_some());
+
+        throttle.clear("user:a").await.unwrap();
+        assert_eq!(throttle.locked_for("user:a").await.unwrap(), None);
+    }
+}
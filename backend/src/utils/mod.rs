@@ -1,3 +1,18 @@
 pub mod app_error;
+pub mod auth_cookies;
+pub mod client_info;
 pub mod jwt;
-pub mod token_wrapper;
+pub mod key_ring;
+pub mod login_throttle;
+pub mod mailer;
+pub mod notifier;
+pub mod oidc;
+pub mod password_hasher;
+pub mod project_role;
+pub mod reminders;
+pub mod retention;
+pub mod role;
+pub mod scope;
+pub mod secure_token;
+pub mod task_status;
+pub mod totp;
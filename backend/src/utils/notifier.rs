@@ -0,0 +1,136 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::async_trait;
+use chrono::{DateTime, FixedOffset};
+use eyre::Result;
+use reqwest::Client;
+use serde::Serialize;
+
+use super::mailer::{Email, SharedMailer};
+
+/// A task reminder that came due, addressed to the task's assignee or, when
+/// nobody is assigned, its creator.
+#[derive(Clone, Debug, Serialize)]
+pub struct Reminder {
+    pub task_id: i32,
+    pub title: String,
+    pub due_at: Option<DateTime<FixedOffset>>,
+    pub remind_at: DateTime<FixedOffset>,
+    pub user_id: i32,
+    /// The user's email address.
+    pub email: String,
+}
+
+/// Delivers reminders. Pick an implementation with the `NOTIFIER`
+/// environment variable, see [`notifier_from_env`].
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, reminder: &Reminder) -> Result<()>;
+}
+
+#[derive(Clone)]
+pub struct SharedNotifier(pub Arc<dyn Notifier>);
+
+/// Prints reminders to stdout, handy for local development.
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, reminder: &Reminder) -> Result<()> {
+        println!(
+            "->> REMINDER task {} {:?} for user {}",
+            reminder.task_id, reminder.title, reminder.user_id
+        );
+
+        Ok(())
+    }
+}
+
+/// Reminders are sent one after the other, a slow webhook must not hold up
+/// the rest for long.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts every reminder as JSON to a URL.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(WEBHOOK_TIMEOUT).build()?,
+            url: url.into(),
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, reminder: &Reminder) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(reminder)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Emails the reminder through the app's mailer.
+pub struct EmailNotifier {
+    mailer: SharedMailer,
+    app_url: String,
+}
+
+impl EmailNotifier {
+    pub fn new(mailer: SharedMailer, app_url: impl Into<String>) -> Self {
+        Self {
+            mailer,
+            app_url: app_url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(&self, reminder: &Reminder) -> Result<()> {
+        let due = match reminder.due_at {
+            Some(due_at) => format!("It is due {}.\n\n", due_at.to_rfc2822()),
+            None => String::new(),
+        };
+
+        self.mailer
+            .0
+            .send(Email {
+                to: reminder.email.clone(),
+                subject: format!("Reminder: {}", reminder.title),
+                body: format!(
+                    "This is your reminder for \"{}\". {}{}/tasks/{}\n",
+                    reminder.title, due, self.app_url, reminder.task_id
+                ),
+            })
+            .await
+    }
+}
+
+/// Builds the notifier selected by `NOTIFIER` (`log`, `webhook` or `email`,
+/// defaults to `log`). The webhook posts to `REMINDER_WEBHOOK_URL`.
+pub fn notifier_from_env(
+    mailer: &SharedMailer, app_url: &str,
+) -> Result<SharedNotifier> {
+    let kind = dotenvy::var("NOTIFIER").unwrap_or_else(|_| "log".to_owned());
+
+    let notifier: Arc<dyn Notifier> = match kind.as_str() {
+        "log" => Arc::new(LogNotifier),
+        "webhook" => Arc::new(WebhookNotifier::new(dotenvy::var(
+            "REMINDER_WEBHOOK_URL",
+        )?)?),
+        "email" => Arc::new(EmailNotifier::new(mailer.clone(), app_url)),
+        other => eyre::bail!("Unknown NOTIFIER {other:?}"),
+    };
+
+    Ok(SharedNotifier(notifier))
+}
@@ -0,0 +1,256 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use eyre::Result;
+use jsonwebtoken::{
+    decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation,
+};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::app_error::AppError;
+
+/// ID tokens must be signed with the provider's published keys, never with
+/// a shared secret.
+const ALLOWED_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// The parts of `/.well-known/openid-configuration` the login flow uses.
+#[derive(Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+}
+
+/// A generic OpenID Connect provider users can sign in with using the
+/// authorization code flow with PKCE.
+#[derive(Clone)]
+pub struct OidcProvider {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+    pub scopes: String,
+    client: Client,
+}
+
+/// Every configured provider by name, as used in the login routes.
+#[derive(Clone, Default)]
+pub struct OidcProviders(pub Arc<HashMap<String, OidcProvider>>);
+
+fn provider_error(error: impl std::fmt::Debug) -> AppError {
+    eprintln!("Error talking to the identity provider: {:?}", error);
+    AppError::new(
+        StatusCode::BAD_GATEWAY,
+        "Could not sign in with the identity provider, please try again later",
+    )
+}
+
+fn invalid_id_token(error: impl std::fmt::Debug) -> AppError {
+    eprintln!("Error validating id token: {:?}", error);
+    AppError::new(StatusCode::UNAUTHORIZED, "invalid id token")
+}
+
+/// The S256 PKCE challenge sent along with the authorization request.
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+impl OidcProvider {
+    pub fn new(
+        name: String, issuer: String, client_id: String,
+        client_secret: Option<String>, redirect_url: String,
+    ) -> Self {
+        Self {
+            name,
+            issuer: issuer.trim_end_matches('/').to_owned(),
+            client_id,
+            client_secret,
+            redirect_url,
+            scopes: "openid email profile".to_owned(),
+            client: Client::new(),
+        }
+    }
+
+    /// Fetches the provider metadata. Logins are rare enough that this is
+    /// not cached, which also picks up endpoint changes without a restart.
+    pub async fn discover(&self) -> Result<ProviderMetadata, AppError> {
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+
+        let metadata: ProviderMetadata = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+
+        if metadata.issuer.trim_end_matches('/') != self.issuer {
+            return Err(provider_error(format!(
+                "discovered issuer {:?} does not match {:?}",
+                metadata.issuer, self.issuer
+            )));
+        }
+
+        Ok(metadata)
+    }
+
+    /// Where to send the browser to sign in.
+    pub fn authorization_url(
+        &self, metadata: &ProviderMetadata, state: &str, nonce: &str,
+        pkce_verifier: &str,
+    ) -> Result<String, AppError> {
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client_id),
+                ("redirect_uri", &self.redirect_url),
+                ("scope", &self.scopes),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", &pkce_challenge(pkce_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(provider_error)?;
+
+        Ok(url.into())
+    }
+
+    /// Trades the authorization code for tokens and returns the validated
+    /// claims of the ID token.
+    pub async fn exchange_code(
+        &self, metadata: &ProviderMetadata, code: &str, pkce_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.redirect_url),
+            ("client_id", &self.client_id),
+            ("code_verifier", pkce_verifier),
+        ];
+
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+
+        let tokens: TokenResponse = self
+            .client
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+
+        self.validate_id_token(metadata, &tokens.id_token, nonce)
+            .await
+    }
+
+    async fn validate_id_token(
+        &self, metadata: &ProviderMetadata, id_token: &str, nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let header = decode_header(id_token).map_err(invalid_id_token)?;
+
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(invalid_id_token(header.alg));
+        }
+
+        let jwks: JwkSet = self
+            .client
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| invalid_id_token("no matching signing key"))?;
+        let key = DecodingKey::from_jwk(jwk).map_err(invalid_id_token)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.client_id]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(invalid_id_token)?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid_id_token("nonce mismatch"));
+        }
+
+        Ok(claims)
+    }
+}
+
+/// Reads the providers listed in `OIDC_PROVIDERS`, each configured through
+/// `OIDC_<NAME>_ISSUER`, `_CLIENT_ID`, `_CLIENT_SECRET` and `_REDIRECT_URL`.
+pub fn oidc_providers_from_env() -> Result<OidcProviders> {
+    let names = dotenvy::var("OIDC_PROVIDERS").unwrap_or_default();
+    let mut providers = HashMap::new();
+
+    for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let var = |key: &str| {
+            dotenvy::var(format!("OIDC_{}_{key}", name.to_uppercase()))
+        };
+
+        let mut provider = OidcProvider::new(
+            name.to_owned(),
+            var("ISSUER")?,
+            var("CLIENT_ID")?,
+            var("CLIENT_SECRET").ok(),
+            var("REDIRECT_URL")?,
+        );
+
+        if let Ok(scopes) = var("SCOPES") {
+            provider.scopes = scopes;
+        }
+
+        providers.insert(name.to_owned(), provider);
+    }
+
+    Ok(OidcProviders(Arc::new(providers)))
+}
@@ -0,0 +1,185 @@
+use argon2::{
+    password_hash::{
+        rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier,
+        SaltString,
+    },
+    Algorithm, Argon2, Params, Version,
+};
+use axum::http::StatusCode;
+use eyre::Result;
+
+use super::app_error::AppError;
+
+/// Which algorithm new hashes are created with. Existing hashes are always
+/// verified with whatever algorithm produced them.
+#[derive(Clone, Copy, Debug)]
+pub enum HashAlgorithm {
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+    Bcrypt {
+        cost: u32,
+    },
+}
+
+impl Default for HashAlgorithm {
+    fn default() -> Self {
+        HashAlgorithm::Argon2id {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// Hashes and verifies passwords on the blocking thread pool, so the slow
+/// key derivation does not stall the async runtime.
+#[derive(Clone, Debug, Default)]
+pub struct PasswordHasher {
+    algorithm: HashAlgorithm,
+}
+
+fn hashing_error(error: impl std::fmt::Debug) -> AppError {
+    eprintln!("Error hashing password: {:?}", error);
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error securing password")
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    hash.starts_with("$2")
+}
+
+impl PasswordHasher {
+    pub fn new(algorithm: HashAlgorithm) -> Result<Self> {
+        if let HashAlgorithm::Argon2id {
+            memory_kib,
+            iterations,
+            parallelism,
+        } = algorithm
+        {
+            Params::new(memory_kib, iterations, parallelism, None).map_err(
+                |error| eyre::eyre!("Invalid argon2 params: {error}"),
+            )?;
+        }
+
+        Ok(Self { algorithm })
+    }
+
+    pub async fn hash(&self, password: String) -> Result<String, AppError> {
+        let algorithm = self.algorithm;
+
+        tokio::task::spawn_blocking(move || match algorithm {
+            HashAlgorithm::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params =
+                    Params::new(memory_kib, iterations, parallelism, None)
+                        .map_err(hashing_error)?;
+                let salt = SaltString::generate(&mut OsRng);
+
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(hashing_error)
+            }
+            HashAlgorithm::Bcrypt { cost } => {
+                bcrypt::hash(password, cost).map_err(hashing_error)
+            }
+        })
+        .await
+        .map_err(hashing_error)?
+    }
+
+    pub async fn verify(
+        &self, password: String, hash: String,
+    ) -> Result<bool, AppError> {
+        tokio::task::spawn_blocking(move || {
+            if is_bcrypt(&hash) {
+                return bcrypt::verify(password, &hash).map_err(hashing_error);
+            }
+
+            let parsed = PasswordHash::new(&hash).map_err(hashing_error)?;
+
+            match Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+            {
+                Ok(()) => Ok(true),
+                Err(argon2::password_hash::Error::Password) => Ok(false),
+                Err(error) => Err(hashing_error(error)),
+            }
+        })
+        .await
+        .map_err(hashing_error)?
+    }
+
+    /// Whether a stored hash was made with a different algorithm or weaker
+    /// parameters than the ones configured now, and should be replaced the
+    /// next time the plain password is known.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        match self.algorithm {
+            HashAlgorithm::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let Ok(parsed) = PasswordHash::new(hash) else {
+                    return true;
+                };
+                let Ok(params) = Params::try_from(&parsed) else {
+                    return true;
+                };
+
+                parsed.algorithm != Algorithm::Argon2id.ident()
+                    || params.m_cost() != memory_kib
+                    || params.t_cost() != iterations
+                    || params.p_cost() != parallelism
+            }
+            HashAlgorithm::Bcrypt { cost } => {
+                // bcrypt hashes look like `$2b$12$...`
+                let stored_cost =
+                    hash.split('$').nth(2).and_then(|c| c.parse::<u32>().ok());
+                !is_bcrypt(hash) || stored_cost != Some(cost)
+            }
+        }
+    }
+}
+
+fn env_number<T: std::str::FromStr>(name: &str, default: T) -> Result<T> {
+    match dotenvy::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| eyre::eyre!("Invalid {name} {value:?}")),
+        Err(_) => Ok(default),
+    }
+}
+
+pub fn password_hasher_from_env() -> Result<PasswordHasher> {
+    let kind =
+        dotenvy::var("PASSWORD_HASH").unwrap_or_else(|_| "argon2id".to_owned());
+
+    let algorithm = match kind.as_str() {
+        "argon2id" => HashAlgorithm::Argon2id {
+            memory_kib: env_number(
+                "ARGON2_MEMORY_KIB",
+                Params::DEFAULT_M_COST,
+            )?,
+            iterations: env_number(
+                "ARGON2_ITERATIONS",
+                Params::DEFAULT_T_COST,
+            )?,
+            parallelism: env_number(
+                "ARGON2_PARALLELISM",
+                Params::DEFAULT_P_COST,
+            )?,
+        },
+        "bcrypt" => HashAlgorithm::Bcrypt {
+            cost: env_number("BCRYPT_COST", bcrypt::DEFAULT_COST)?,
+        },
+        other => eyre::bail!("Unknown PASSWORD_HASH {other:?}"),
+    };
+
+    PasswordHasher::new(algorithm)
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// What a member can do in a shared project, each role including the ones
+/// before it.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectRole {
+    /// Sees the project and its tasks.
+    Viewer,
+    /// Also creates, changes and deletes tasks.
+    Editor,
+    /// Also manages the project itself and who is in it.
+    Owner,
+}
+
+impl ProjectRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectRole::Viewer => "viewer",
+            ProjectRole::Editor => "editor",
+            ProjectRole::Owner => "owner",
+        }
+    }
+
+    /// Reads a role stored on a membership or invitation row. Anything
+    /// unrecognised is treated as the least privileged role.
+    pub fn parse(role: &str) -> ProjectRole {
+        match role {
+            "owner" => ProjectRole::Owner,
+            "editor" => ProjectRole::Editor,
+            _ => ProjectRole::Viewer,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::Utc;
+use sea_orm::{prelude::DateTimeWithTimeZone, DatabaseConnection};
+
+use super::notifier::{Reminder, SharedNotifier};
+use crate::queires::{
+    task_queries::{find_due_reminders, mark_task_reminded},
+    user_queries::find_users_by_ids,
+};
+
+/// How often the scheduler looks for reminders that came due.
+const REMINDER_INTERVAL: Duration = Duration::from_secs(60);
+
+async fn send_reminders(db: &DatabaseConnection, notifier: &SharedNotifier) {
+    let now: DateTimeWithTimeZone = Utc::now().into();
+
+    let Ok(tasks) = find_due_reminders(db, now).await else {
+        return;
+    };
+
+    let user_ids = tasks
+        .iter()
+        .flat_map(|task| [task.assignee_id, task.user_id])
+        .flatten()
+        .collect();
+    let Ok(users) = find_users_by_ids(db, user_ids).await else {
+        return;
+    };
+    let users = users
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect::<HashMap<_, _>>();
+
+    for task in tasks {
+        // Only active accounts are loaded, so a deleted assignee falls back
+        // to the creator.
+        let user = [task.assignee_id, task.user_id]
+            .into_iter()
+            .flatten()
+            .find_map(|user_id| users.get(&user_id));
+
+        // Neither is left to remind, the reminder is dropped. Errors marking
+        // it are already logged, the next run tries again.
+        let Some(user) = user else {
+            let _ = mark_task_reminded(db, &task, now).await;
+            continue;
+        };
+
+        let reminder = Reminder {
+            task_id: task.id,
+            title: task.title.clone(),
+            due_at: task.due_at,
+            remind_at: task.remind_at.unwrap_or(now),
+            user_id: user.id,
+            email: user.username.clone(),
+        };
+
+        // A failed delivery is retried on the next run.
+        match notifier.0.notify(&reminder).await {
+            Ok(()) => {
+                let _ = mark_task_reminded(db, &task, now).await;
+            }
+            Err(error) => {
+                eprintln!("Error sending reminder: {:?}", error);
+            }
+        }
+    }
+}
+
+/// Fires the task reminders in the background for as long as the server is
+/// up, checking every minute.
+pub fn spawn_reminder_job(db: DatabaseConnection, notifier: SharedNotifier) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REMINDER_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            send_reminders(&db, &notifier).await;
+        }
+    });
+}
@@ -0,0 +1,112 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use eyre::Result;
+use sea_orm::DatabaseConnection;
+
+use super::login_throttle::SharedLoginThrottle;
+use crate::queires::{
+    task_queries::purge_trashed_tasks, user_queries::purge_deleted_users,
+};
+
+/// How often the purge job looks for expired rows.
+const PURGE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+/// How long soft-deleted rows are kept around to be restored before they
+/// are deleted for good.
+#[derive(Clone, Debug)]
+pub struct RetentionConfig {
+    pub deleted_users: Duration,
+    pub trashed_tasks: Duration,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            deleted_users: Duration::try_days(30)
+                .expect("Failed to create duration"),
+            trashed_tasks: Duration::try_days(30)
+                .expect("Failed to create duration"),
+        }
+    }
+}
+
+async fn purge(
+    db: &DatabaseConnection, config: &RetentionConfig,
+    throttle: &SharedLoginThrottle,
+) {
+    let now = Utc::now();
+
+    // Errors are already logged, the next run tries again.
+    if let Ok(count @ 1..) =
+        purge_deleted_users(db, now - config.deleted_users).await
+    {
+        println!("->> PURGED {count} deleted users");
+    }
+
+    if let Ok(count @ 1..) =
+        purge_trashed_tasks(db, now - config.trashed_tasks).await
+    {
+        println!("->> PURGED {count} trashed tasks");
+    }
+
+    match throttle.0.sweep().await {
+        Ok(count @ 1..) => println!("->> PURGED {count} login attempts"),
+        Ok(_) => {}
+        Err(error) => eprintln!("Error purging login attempts: {:?}", error),
+    }
+}
+
+/// Runs the purge in the background for as long as the server is up, once
+/// at startup and then every hour. Expired login attempts go with it.
+pub fn spawn_purge_job(
+    db: DatabaseConnection, config: RetentionConfig,
+    throttle: SharedLoginThrottle,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            purge(&db, &config, &throttle).await;
+        }
+    });
+}
+
+/// A negative retention would put the cutoff in the future and purge every
+/// soft-deleted row at once, so it is rejected along with ones too large to
+/// compute a cutoff for.
+fn days_from_env(key: &str) -> Result<Option<Duration>> {
+    match dotenvy::var(key) {
+        Ok(days) => {
+            let duration = days
+                .parse::<i64>()
+                .ok()
+                .filter(|days| *days >= 0)
+                .and_then(Duration::try_days)
+                // The purge subtracts it from the current time.
+                .filter(|duration| {
+                    Utc::now().checked_sub_signed(*duration).is_some()
+                })
+                .ok_or_else(|| eyre::eyre!("Invalid {key} {days:?}"))?;
+            Ok(Some(duration))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Reads `DELETED_USER_RETENTION_DAYS` and `TASK_TRASH_RETENTION_DAYS`, both
+/// defaulting to 30 days.
+pub fn retention_config_from_env() -> Result<RetentionConfig> {
+    let mut config = RetentionConfig::default();
+
+    if let Some(deleted_users) = days_from_env("DELETED_USER_RETENTION_DAYS")? {
+        config.deleted_users = deleted_users;
+    }
+
+    if let Some(trashed_tasks) = days_from_env("TASK_TRASH_RETENTION_DAYS")? {
+        config.trashed_tasks = trashed_tasks;
+    }
+
+    Ok(config)
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::database::users::Model as UserModel;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Reads the role stored on a user row. Anything unrecognised is treated
+    /// as the least privileged role.
+    pub fn of(user: &UserModel) -> Role {
+        match user.role.as_str() {
+            "admin" => Role::Admin,
+            _ => Role::User,
+        }
+    }
+}
+
+pub fn is_admin(user: &UserModel) -> bool {
+    Role::of(user) == Role::Admin
+}
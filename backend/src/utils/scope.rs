@@ -0,0 +1,133 @@
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+
+/// What a personal access token is allowed to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "tasks:read")]
+    TasksRead,
+    #[serde(rename = "tasks:write")]
+    TasksWrite,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::TasksRead => "tasks:read",
+            Scope::TasksWrite => "tasks:write",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Scope> {
+        match scope {
+            "tasks:read" => Some(Scope::TasksRead),
+            "tasks:write" => Some(Scope::TasksWrite),
+            _ => None,
+        }
+    }
+
+    /// Scopes are stored space separated, unknown ones are dropped.
+    pub fn parse_list(scopes: &str) -> Vec<Scope> {
+        scopes.split_whitespace().filter_map(Scope::parse).collect()
+    }
+
+    pub fn join(scopes: &[Scope]) -> String {
+        scopes
+            .iter()
+            .map(Scope::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The scope a request needs when made with a personal access token.
+    /// Tokens can read the projects tasks are filed under, but managing
+    /// projects, their members and invitations is left to the owner's
+    /// session, just like account or token management.
+    pub fn required_for(method: &Method, path: &str) -> Option<Scope> {
+        let is_task_path = path
+            .strip_prefix("/tasks")
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        let is_read = matches!(*method, Method::GET | Method::HEAD);
+
+        let allowed = is_task_path || (is_read && is_project_read_path(path));
+        if !allowed {
+            return None;
+        }
+
+        match *method {
+            Method::GET | Method::HEAD => Some(Scope::TasksRead),
+            _ => Some(Scope::TasksWrite),
+        }
+    }
+}
+
+/// `/projects`, `/projects/:id` and `/projects/:id/tasks`.
+fn is_project_read_path(path: &str) -> bool {
+    let Some(rest) = path.strip_prefix("/projects") else {
+        return false;
+    };
+
+    match rest.strip_prefix('/') {
+        None => rest.is_empty(),
+        Some(rest) => match rest.split_once('/') {
+            None => !rest.is_empty(),
+            Some((id, rest)) => !id.is_empty() && rest == "tasks",
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_routes_need_read_or_write() {
+        assert_eq!(
+            Scope::required_for(&Method::GET, "/tasks"),
+            Some(Scope::TasksRead)
+        );
+        assert_eq!(
+            Scope::required_for(&Method::HEAD, "/tasks/4/items"),
+            Some(Scope::TasksRead)
+        );
+        assert_eq!(
+            Scope::required_for(&Method::POST, "/tasks"),
+            Some(Scope::TasksWrite)
+        );
+        assert_eq!(
+            Scope::required_for(&Method::DELETE, "/tasks/4"),
+            Some(Scope::TasksWrite)
+        );
+    }
+
+    #[test]
+    fn projects_are_read_only() {
+        for path in ["/projects", "/projects/3", "/projects/3/tasks"] {
+            assert_eq!(
+                Scope::required_for(&Method::GET, path),
+                Some(Scope::TasksRead)
+            );
+            assert_eq!(Scope::required_for(&Method::PATCH, path), None);
+        }
+
+        for path in ["/projects/3/members", "/projects/3/invitations"] {
+            assert_eq!(Scope::required_for(&Method::GET, path), None);
+        }
+    }
+
+    #[test]
+    fn other_routes_are_closed() {
+        for path in ["/taskset", "/users/me", "/users/me/tokens", "/projects/"]
+        {
+            assert_eq!(Scope::required_for(&Method::GET, path), None);
+        }
+    }
+
+    #[test]
+    fn unknown_scopes_are_dropped() {
+        assert_eq!(
+            Scope::parse_list("tasks:read admin tasks:write"),
+            vec![Scope::TasksRead, Scope::TasksWrite]
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Generates a random, URL-safe token to hand out to clients once.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hashes a token before it is persisted, so a database leak does not
+/// expose usable credentials.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Compares two tokens without leaking through timing how much of them
+/// matched.
+pub fn tokens_match(a: &str, b: &str) -> bool {
+    hash_token(a) == hash_token(b)
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::database::tasks::Model as TaskModel;
+
+/// Where a task is in its lifecycle. `completed_at` is kept in step with it:
+/// set when the task is done, cleared when it is reopened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Todo,
+    InProgress,
+    Done,
+    Archived,
+}
+
+impl TaskStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Todo => "todo",
+            TaskStatus::InProgress => "in_progress",
+            TaskStatus::Done => "done",
+            TaskStatus::Archived => "archived",
+        }
+    }
+
+    /// Reads the status stored on a task row, falling back to `todo` for
+    /// anything unrecognised.
+    pub fn of(task: &TaskModel) -> TaskStatus {
+        match task.status.as_str() {
+            "in_progress" => TaskStatus::InProgress,
+            "done" => TaskStatus::Done,
+            "archived" => TaskStatus::Archived,
+            _ => TaskStatus::Todo,
+        }
+    }
+
+    /// Archived tasks have to be reopened before work on them can resume.
+    pub fn can_move_to(&self, next: TaskStatus) -> bool {
+        use TaskStatus::*;
+
+        matches!(
+            (self, next),
+            (Todo, InProgress | Done | Archived)
+                | (InProgress, Todo | Done | Archived)
+                | (Done, Todo | InProgress | Archived)
+                | (Archived, Todo)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TaskStatus::*;
+
+    #[test]
+    fn open_tasks_move_freely() {
+        assert!(Todo.can_move_to(InProgress));
+        assert!(InProgress.can_move_to(Done));
+        assert!(Done.can_move_to(Todo));
+        assert!(InProgress.can_move_to(Archived));
+    }
+
+    #[test]
+    fn archived_tasks_are_reopened_first() {
+        assert!(Archived.can_move_to(Todo));
+        assert!(!Archived.can_move_to(InProgress));
+        assert!(!Archived.can_move_to(Done));
+    }
+
+    #[test]
+    fn staying_put_is_not_a_move() {
+        for status in [Todo, InProgress, Done, Archived] {
+            assert!(!status.can_move_to(status));
+        }
+    }
+}
@@ -1,2 +0,0 @@
-#[derive(Clone)]
-pub struct TokenWrapper(pub String);
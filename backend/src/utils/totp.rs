@@ -0,0 +1,116 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use super::app_error::AppError;
+
+/// Name authenticator apps show next to the account.
+const TOTP_ISSUER: &str = "Tasks";
+
+const TOTP_STEP_SECONDS: u64 = 30;
+
+fn totp_error(error: impl std::fmt::Debug) -> AppError {
+    eprintln!("Error using totp secret: {:?}", error);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "There was an error, please try again later",
+    )
+}
+
+/// Generates a new base32 encoded secret to enroll an authenticator with.
+pub fn generate_totp_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+/// RFC 6238 generator for the secret, with the defaults every authenticator
+/// app understands: SHA-1, 6 digits and a 30 second step. Drift is handled
+/// by [`check_totp_code`] so it knows which step a code belongs to.
+fn totp(secret: &str, account: &str) -> Result<TOTP, AppError> {
+    let bytes = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .map_err(totp_error)?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        TOTP_STEP_SECONDS,
+        bytes,
+        Some(TOTP_ISSUER.to_owned()),
+        account.to_owned(),
+    )
+    .map_err(totp_error)
+}
+
+/// The `otpauth://` URI authenticator apps import, usually as a QR code.
+pub fn totp_url(secret: &str, account: &str) -> Result<String, AppError> {
+    Ok(totp(secret, account)?.get_url())
+}
+
+/// Checks the code against the current time step and one step of drift
+/// either way, returning the step it matched. Steps at or before
+/// `last_step` are skipped so a code that was already used cannot be
+/// replayed (RFC 6238 §5.2); the caller records the returned step.
+pub fn check_totp_code(
+    secret: &str, account: &str, code: &str, last_step: Option<i64>,
+) -> Result<Option<i64>, AppError> {
+    let totp = totp(secret, account)?;
+    let current = Utc::now().timestamp() / TOTP_STEP_SECONDS as i64;
+
+    let step = (current - 1..=current + 1)
+        .filter(|step| last_step.is_none_or(|last| *step > last))
+        .find(|step| totp.check(code.trim(), *step as u64 * TOTP_STEP_SECONDS));
+
+    Ok(step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_at(secret: &str, step: i64) -> String {
+        totp(secret, "user@example.com")
+            .unwrap()
+            .generate(step as u64 * TOTP_STEP_SECONDS)
+    }
+
+    fn current_step() -> i64 {
+        Utc::now().timestamp() / TOTP_STEP_SECONDS as i64
+    }
+
+    fn check(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+        check_totp_code(secret, "user@example.com", code, last_step).unwrap()
+    }
+
+    #[test]
+    fn codes_match_their_step() {
+        let secret = generate_totp_secret();
+        let step = current_step();
+
+        assert_eq!(check(&secret, &code_at(&secret, step), None), Some(step));
+        assert_eq!(
+            check(&secret, &code_at(&secret, step - 1), None),
+            Some(step - 1)
+        );
+    }
+
+    #[test]
+    fn used_steps_are_not_accepted_again() {
+        let secret = generate_totp_secret();
+        let step = current_step();
+        let code = code_at(&secret, step);
+
+        assert_eq!(check(&secret, &code, Some(step)), None);
+        assert_eq!(check(&secret, &code, Some(step - 1)), Some(step));
+    }
+
+    #[test]
+    fn codes_outside_the_window_are_rejected() {
+        let secret = generate_totp_secret();
+
+        assert_eq!(
+            check(&secret, &code_at(&secret, current_step() - 5), None),
+            None
+        );
+    }
+}
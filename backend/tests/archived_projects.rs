@@ -0,0 +1,56 @@
+mod common;
+
+use common::{spawn_app, TestApp, TestUser};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+async fn create_project(app: &TestApp, user: &TestUser, name: &str) -> i64 {
+    let project: Value = app
+        .post("/projects")
+        .bearer_auth(&user.token)
+        .json(&json!({ "name": name }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    project["id"].as_i64().unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn archived_projects_take_no_new_tasks() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let project_id = create_project(&app, &owner, "archived").await;
+    let task_id = app.create_task(&owner, "in the inbox").await;
+
+    let response = app
+        .patch(&format!("/projects/{project_id}"))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "archived": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .post("/tasks")
+        .bearer_auth(&owner.token)
+        .json(&json!({ "title": "new", "project_id": project_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app
+        .patch(&format!("/tasks/{task_id}"))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "project_id": project_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
@@ -0,0 +1,300 @@
+#![allow(dead_code)]
+
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+};
+
+use reqwest::{redirect::Policy, Client, RequestBuilder, StatusCode};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
+use serde_json::{json, Value};
+use tokio::sync::OnceCell;
+use web_app::{
+    app_state::{AppConfig, AppState},
+    create_routes,
+    utils::{
+        jwt::JwtIssuer,
+        key_ring::{KeyRing, SigningKey},
+        login_throttle::{MemoryLoginThrottle, SharedLoginThrottle},
+        mailer::{MemoryMailer, SharedMailer},
+        notifier::{LogNotifier, SharedNotifier},
+        oidc::OidcProviders,
+        password_hasher::{HashAlgorithm, PasswordHasher},
+    },
+};
+
+pub const PASSWORD: &str = "correct horse battery";
+
+static SCHEMA: OnceCell<()> = OnceCell::const_new();
+
+/// The app served on a random port, backed by the database in
+/// `TEST_DATABASE_URL`.
+pub struct TestApp {
+    pub address: String,
+    pub db: DatabaseConnection,
+    pub mailer: MemoryMailer,
+    pub client: Client,
+}
+
+pub struct TestUser {
+    pub id: i32,
+    pub username: String,
+    pub token: String,
+    pub refresh_token: String,
+}
+
+/// Starts the app. The tests using it are `#[ignore]`d, run them with
+/// `cargo test -- --ignored` once `TEST_DATABASE_URL` is set.
+pub async fn spawn_app() -> TestApp {
+    spawn_app_with_oidc(OidcProviders::default()).await
+}
+
+pub async fn spawn_app_with_oidc(oidc: OidcProviders) -> TestApp {
+    let database_url = dotenvy::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL must be set to run the request tests");
+
+    let db = Database::connect(database_url)
+        .await
+        .expect("Failed to connect to the test database");
+
+    // init.sql is idempotent, running it once per test binary keeps the
+    // tests from racing each other on the schema.
+    SCHEMA
+        .get_or_init(|| async {
+            db.execute_unprepared(include_str!("../../database/init.sql"))
+                .await
+                .expect("Failed to apply init.sql");
+        })
+        .await;
+
+    let mailer = MemoryMailer::default();
+    let app_state = AppState {
+        db: db.clone(),
+        jwt: JwtIssuer {
+            config: Default::default(),
+            keys: Arc::new(
+                KeyRing::new(vec![SigningKey::hmac("test", "secret")], "test")
+                    .unwrap(),
+            ),
+        },
+        mailer: SharedMailer(Arc::new(mailer.clone())),
+        notifier: SharedNotifier(Arc::new(LogNotifier)),
+        login_throttle: SharedLoginThrottle(Arc::new(
+            MemoryLoginThrottle::default(),
+        )),
+        // the cheapest hash keeps signups fast
+        password_hasher: PasswordHasher::new(HashAlgorithm::Bcrypt { cost: 4 })
+            .unwrap(),
+        oidc,
+        config: AppConfig {
+            app_url: "http://localhost:8080".to_owned(),
+            auth_cookie: Default::default(),
+            retention: Default::default(),
+        },
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let app = create_routes(app_state).await;
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>()),
+    );
+
+    let client = Client::builder().redirect(Policy::none()).build().unwrap();
+
+    TestApp {
+        address,
+        db,
+        mailer,
+        client,
+    }
+}
+
+/// An email nobody else signed up with, every test shares the database.
+pub fn unique_email(name: &str) -> String {
+    format!(
+        "{name}-{}@example.com",
+        hex::encode(rand::random::<[u8; 6]>())
+    )
+}
+
+impl TestUser {
+    async fn from_response(
+        username: String, response: reqwest::Response,
+    ) -> TestUser {
+        let body: Value = response.json().await.unwrap();
+
+        TestUser {
+            id: body["id"].as_i64().unwrap() as i32,
+            token: body["token"].as_str().unwrap().to_owned(),
+            refresh_token: body["refresh_token"].as_str().unwrap().to_owned(),
+            username,
+        }
+    }
+}
+
+impl TestApp {
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.client.get(format!("{}{path}", self.address))
+    }
+
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.client.post(format!("{}{path}", self.address))
+    }
+
+    pub fn put(&self, path: &str) -> RequestBuilder {
+        self.client.put(format!("{}{path}", self.address))
+    }
+
+    pub fn patch(&self, path: &str) -> RequestBuilder {
+        self.client.patch(format!("{}{path}", self.address))
+    }
+
+    pub fn delete(&self, path: &str) -> RequestBuilder {
+        self.client.delete(format!("{}{path}", self.address))
+    }
+
+    /// Signs up through `POST /users`, leaving the email unverified.
+    pub async fn sign_up(&self, name: &str) -> TestUser {
+        let username = unique_email(name);
+        let response = self
+            .post("/users")
+            .json(&json!({ "username": username, "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        TestUser::from_response(username, response).await
+    }
+
+    /// Opens another session for the user with their password.
+    pub async fn log_in(&self, user: &TestUser) -> TestUser {
+        let response = self
+            .post("/users/login")
+            .json(&json!({ "username": user.username, "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        TestUser::from_response(user.username.clone(), response).await
+    }
+
+    /// A user that confirmed their email, so the task routes are open.
+    pub async fn verified_user(&self, name: &str) -> TestUser {
+        let user = self.sign_up(name).await;
+        self.sql(&format!(
+            "UPDATE users SET verified_at = NOW() WHERE id = {}",
+            user.id
+        ))
+        .await;
+        user
+    }
+
+    pub async fn admin(&self) -> TestUser {
+        let user = self.verified_user("admin").await;
+        self.sql(&format!(
+            "UPDATE users SET role = 'admin' WHERE id = {}",
+            user.id
+        ))
+        .await;
+        user
+    }
+
+    pub async fn sql(&self, sql: &str) {
+        self.db.execute_unprepared(sql).await.unwrap();
+    }
+
+    /// A personal access token of the user with every scope.
+    pub async fn create_access_token(&self, user: &TestUser) -> String {
+        let response = self
+            .post("/users/me/tokens")
+            .bearer_auth(&user.token)
+            .json(&json!({
+                "name": "cli",
+                "scopes": ["tasks:read", "tasks:write"],
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body: Value = response.json().await.unwrap();
+        body["token"].as_str().unwrap().to_owned()
+    }
+
+    /// The status `GET /tasks` answers with for the bearer token.
+    pub async fn list_tasks(&self, token: &str) -> StatusCode {
+        self.get("/tasks")
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    /// The status `POST /users/refresh` answers with for the refresh token.
+    pub async fn refresh(&self, refresh_token: &str) -> StatusCode {
+        self.post("/users/refresh")
+            .json(&json!({ "refresh_token": refresh_token }))
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    pub async fn create_task(&self, user: &TestUser, title: &str) -> i32 {
+        let response = self
+            .post("/tasks")
+            .bearer_auth(&user.token)
+            .json(&json!({ "title": title }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body: Value = response.json().await.unwrap();
+        body["id"].as_i64().unwrap() as i32
+    }
+
+    /// Creates a project owned by `owner` that `member` has joined with
+    /// `role`, returning its id.
+    pub async fn shared_project(
+        &self, owner: &TestUser, member: &TestUser, role: &str,
+    ) -> i32 {
+        let project: Value = self
+            .post("/projects")
+            .bearer_auth(&owner.token)
+            .json(&json!({ "name": "shared" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let project_id = project["id"].as_i64().unwrap() as i32;
+
+        let invitation: Value = self
+            .post(&format!("/projects/{project_id}/invitations"))
+            .bearer_auth(&owner.token)
+            .json(&json!({ "email": member.username, "role": role }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let response = self
+            .post(&format!("/invitations/{}/accept", invitation["id"]))
+            .bearer_auth(&member.token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        project_id
+    }
+}
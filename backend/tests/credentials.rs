@@ -0,0 +1,118 @@
+mod common;
+
+use common::{spawn_app, PASSWORD};
+use reqwest::{header, StatusCode};
+use serde_json::json;
+use web_app::utils::auth_cookies::{AUTH_COOKIE, CSRF_COOKIE};
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn signing_up_sets_the_session_cookies() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post("/users")
+        .json(&json!({
+            "username": common::unique_email("cookies"),
+            "password": PASSWORD,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let cookies = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|cookie| cookie.to_str().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    for name in [AUTH_COOKIE, CSRF_COOKIE] {
+        assert!(
+            cookies
+                .iter()
+                .any(|cookie| cookie.starts_with(&format!("{name}="))),
+            "{name} was not set"
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn changing_the_password_signs_out_everything_else() {
+    let app = spawn_app().await;
+    let other_device = app.verified_user("owner").await;
+    let this_device = app.log_in(&other_device).await;
+    let access_token = app.create_access_token(&other_device).await;
+    assert_eq!(app.list_tasks(&access_token).await, StatusCode::OK);
+
+    let response = app
+        .patch("/users/me")
+        .bearer_auth(&this_device.token)
+        .json(&json!({
+            "password": "a brand new password",
+            "current_password": PASSWORD,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(
+        app.list_tasks(&other_device.token).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.refresh(&other_device.refresh_token).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.list_tasks(&access_token).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    assert_eq!(app.list_tasks(&this_device.token).await, StatusCode::OK);
+    assert_eq!(
+        app.refresh(&this_device.refresh_token).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn a_password_set_by_an_admin_signs_the_user_out() {
+    let app = spawn_app().await;
+    let user = app.verified_user("owner").await;
+    let admin = app.admin().await;
+    let access_token = app.create_access_token(&user).await;
+
+    let response = app
+        .patch(&format!("/users/{}", user.id))
+        .bearer_auth(&admin.token)
+        .json(&json!({ "password": "set by the admin" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(app.list_tasks(&user.token).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        app.refresh(&user.refresh_token).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.list_tasks(&access_token).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn refreshing_revokes_the_previous_access_token() {
+    let app = spawn_app().await;
+    let user = app.verified_user("owner").await;
+
+    assert_eq!(app.refresh(&user.refresh_token).await, StatusCode::OK);
+
+    assert_eq!(app.list_tasks(&user.token).await, StatusCode::UNAUTHORIZED);
+}
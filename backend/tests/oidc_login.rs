@@ -0,0 +1,246 @@
+mod common;
+
+use std::{collections::HashMap, net::TcpListener, sync::Arc};
+
+use axum::{extract::Form, routing::get, routing::post, Json, Router};
+use chrono::Utc;
+use common::{spawn_app_with_oidc, TestApp, PASSWORD};
+use ed25519_dalek::pkcs8::{spki::der::pem::LineEnding, EncodePrivateKey};
+use jsonwebtoken::{encode, Header};
+use reqwest::{header, StatusCode, Url};
+use serde_json::{json, Value};
+use web_app::utils::{
+    key_ring::{KeyRing, SigningKey},
+    oidc::{OidcProvider, OidcProviders},
+};
+
+const CLIENT_ID: &str = "web-app";
+
+/// An identity provider serving discovery, its keys and a token endpoint.
+/// The authorization code is the ID token itself, so each test decides what
+/// the provider vouches for.
+struct MockIssuer {
+    url: String,
+    keys: Arc<KeyRing>,
+}
+
+impl MockIssuer {
+    fn start() -> Self {
+        let secret = ed25519_dalek::SigningKey::from_bytes(&rand::random());
+        let pem = secret.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let keys = Arc::new(
+            KeyRing::new(
+                vec![SigningKey::ed25519_pem("mock", &pem).unwrap()],
+                "mock",
+            )
+            .unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let metadata = json!({
+            "issuer": url,
+            "authorization_endpoint": format!("{url}/authorize"),
+            "token_endpoint": format!("{url}/token"),
+            "jwks_uri": format!("{url}/jwks"),
+        });
+        let jwks = keys.jwks();
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(|| async move { Json(metadata) }),
+            )
+            .route("/jwks", get(|| async move { Json(jwks) }))
+            .route(
+                "/token",
+                post(|Form(form): Form<HashMap<String, String>>| async move {
+                    Json(json!({ "id_token": form["code"] }))
+                }),
+            );
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        Self { url, keys }
+    }
+
+    fn providers(&self) -> OidcProviders {
+        let provider = OidcProvider::new(
+            "mock".to_owned(),
+            self.url.clone(),
+            CLIENT_ID.to_owned(),
+            None,
+            "http://localhost:3000/users/login/oidc/mock/callback".to_owned(),
+        );
+
+        OidcProviders(Arc::new(HashMap::from([("mock".to_owned(), provider)])))
+    }
+
+    fn id_token(
+        &self, sub: &str, nonce: &str, email: &str, email_verified: bool,
+    ) -> String {
+        let key = self.keys.signing_key();
+        let mut token_header = Header::new(key.algorithm);
+        token_header.kid = Some(key.kid.clone());
+        let claims = json!({
+            "iss": self.url,
+            "aud": CLIENT_ID,
+            "sub": sub,
+            "exp": Utc::now().timestamp() + 300,
+            "nonce": nonce,
+            "email": email,
+            "email_verified": email_verified,
+        });
+
+        encode(&token_header, &claims, &key.encoding).unwrap()
+    }
+}
+
+/// Goes through the whole redirect flow as the browser would, signing in
+/// at the provider as `sub`.
+async fn sign_in(
+    app: &TestApp, issuer: &MockIssuer, sub: &str, email: &str,
+    email_verified: bool,
+) -> reqwest::Response {
+    let response = app.get("/users/login/oidc/mock").send().await.unwrap();
+    assert!(response.status().is_redirection());
+
+    let cookies = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().unwrap().split(';').next())
+        .collect::<Vec<_>>()
+        .join("; ");
+    let location = response.headers()[header::LOCATION].to_str().unwrap();
+    let params: HashMap<String, String> = Url::parse(location)
+        .unwrap()
+        .query_pairs()
+        .into_owned()
+        .collect();
+    assert!(location.starts_with(&format!("{}/authorize", issuer.url)));
+
+    let code = issuer.id_token(sub, &params["nonce"], email, email_verified);
+    app.get("/users/login/oidc/mock/callback")
+        .query(&[("code", code.as_str()), ("state", &params["state"])])
+        .header(header::COOKIE, cookies)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn login_with_password(app: &TestApp, username: &str) -> StatusCode {
+    app.post("/users/login")
+        .json(&json!({ "username": username, "password": PASSWORD }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn first_sign_in_creates_an_account() {
+    let issuer = MockIssuer::start();
+    let app = spawn_app_with_oidc(issuer.providers()).await;
+    let email = common::unique_email("sso");
+    let sub = common::unique_email("sub");
+
+    let response = sign_in(&app, &issuer, &sub, &email, true).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user: Value = response.json().await.unwrap();
+    assert_eq!(user["username"], email);
+    assert_eq!(user["verified"], true);
+    assert!(user["token"].is_string());
+
+    // later sign ins find the account through the linked identity
+    let response = sign_in(&app, &issuer, &sub, &email, true).await;
+    let again: Value = response.json().await.unwrap();
+    assert_eq!(again["id"], user["id"]);
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn verified_accounts_are_linked() {
+    let issuer = MockIssuer::start();
+    let app = spawn_app_with_oidc(issuer.providers()).await;
+    let existing = app.verified_user("linked").await;
+
+    let response = sign_in(
+        &app,
+        &issuer,
+        &common::unique_email("linked"),
+        &existing.username,
+        true,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user: Value = response.json().await.unwrap();
+    assert_eq!(user["id"], existing.id);
+
+    // the account keeps working as before
+    assert_eq!(
+        login_with_password(&app, &existing.username).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn unverified_accounts_are_reclaimed() {
+    let issuer = MockIssuer::start();
+    let app = spawn_app_with_oidc(issuer.providers()).await;
+    let existing = app.sign_up("squatted").await;
+
+    let response = sign_in(
+        &app,
+        &issuer,
+        &common::unique_email("reclaim"),
+        &existing.username,
+        true,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user: Value = response.json().await.unwrap();
+    assert_eq!(user["id"], existing.id);
+    assert_eq!(user["verified"], true);
+
+    // whoever signed up with the address first loses access
+    let response = app
+        .get("/users/me")
+        .bearer_auth(&existing.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        login_with_password(&app, &existing.username).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn unverified_provider_emails_are_not_linked() {
+    let issuer = MockIssuer::start();
+    let app = spawn_app_with_oidc(issuer.providers()).await;
+    let existing = app.verified_user("taken").await;
+
+    let response = sign_in(
+        &app,
+        &issuer,
+        &common::unique_email("taken"),
+        &existing.username,
+        false,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    assert_eq!(
+        login_with_password(&app, &existing.username).await,
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,287 @@
+mod common;
+
+use common::{spawn_app, TestApp, TestUser};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+async fn patch_task(
+    app: &TestApp, user: &TestUser, task_id: i32,
+) -> StatusCode {
+    app.patch(&format!("/tasks/{task_id}"))
+        .bearer_auth(&user.token)
+        .json(&json!({ "title": "patched" }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn put_task(app: &TestApp, user: &TestUser, task_id: i32) -> StatusCode {
+    app.put(&format!("/tasks/{task_id}"))
+        .bearer_auth(&user.token)
+        .json(&json!({ "title": "replaced" }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn delete_task(
+    app: &TestApp, user: &TestUser, task_id: i32,
+) -> StatusCode {
+    app.delete(&format!("/tasks/{task_id}"))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn tasks_are_hidden_from_other_users() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let other = app.verified_user("other").await;
+    let task_id = app.create_task(&owner, "mine").await;
+
+    assert_eq!(
+        patch_task(&app, &other, task_id).await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(put_task(&app, &other, task_id).await, StatusCode::NOT_FOUND);
+    assert_eq!(
+        delete_task(&app, &other, task_id).await,
+        StatusCode::NOT_FOUND
+    );
+
+    assert_eq!(patch_task(&app, &owner, task_id).await, StatusCode::OK);
+    assert_eq!(put_task(&app, &owner, task_id).await, StatusCode::OK);
+    assert_eq!(delete_task(&app, &owner, task_id).await, StatusCode::OK);
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn admins_can_change_any_task() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let admin = app.admin().await;
+    let task_id = app.create_task(&owner, "mine").await;
+
+    assert_eq!(patch_task(&app, &admin, task_id).await, StatusCode::OK);
+    assert_eq!(put_task(&app, &admin, task_id).await, StatusCode::OK);
+    assert_eq!(delete_task(&app, &admin, task_id).await, StatusCode::OK);
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn project_viewers_cannot_change_tasks() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let viewer = app.verified_user("viewer").await;
+
+    let project_id = app.shared_project(&owner, &viewer, "viewer").await;
+
+    let task: Value = app
+        .post("/tasks")
+        .bearer_auth(&owner.token)
+        .json(&json!({ "title": "shared", "project_id": project_id }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let task_id = task["id"].as_i64().unwrap() as i32;
+
+    let response = app
+        .get(&format!("/tasks/{task_id}"))
+        .bearer_auth(&viewer.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(
+        patch_task(&app, &viewer, task_id).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        put_task(&app, &viewer, task_id).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        delete_task(&app, &viewer, task_id).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn missing_tasks_are_not_found() {
+    let app = spawn_app().await;
+    let admin = app.admin().await;
+
+    assert_eq!(
+        patch_task(&app, &admin, i32::MAX).await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+async fn patch_user(
+    app: &TestApp, user: &TestUser, user_id: i32, body: Value,
+) -> StatusCode {
+    app.patch(&format!("/users/{user_id}"))
+        .bearer_auth(&user.token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn users_can_only_be_patched_by_themselves_or_admins() {
+    let app = spawn_app().await;
+    let owner = app.sign_up("owner").await;
+    let other = app.sign_up("other").await;
+    let admin = app.admin().await;
+
+    assert_eq!(
+        patch_user(&app, &other, owner.id, json!({})).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        patch_user(&app, &owner, owner.id, json!({})).await,
+        StatusCode::OK
+    );
+    // credentials and deletion go through `/users/me` or an admin
+    assert_eq!(
+        patch_user(
+            &app,
+            &owner,
+            owner.id,
+            json!({ "password": "new password" })
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        patch_user(
+            &app,
+            &admin,
+            owner.id,
+            json!({ "password": "new password" })
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        patch_user(&app, &admin, i32::MAX, json!({})).await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+/// The ids of the user's sessions, the one the token belongs to first.
+async fn session_ids(app: &TestApp, user: &TestUser) -> Vec<i64> {
+    let sessions: Value = app
+        .get("/users/me/sessions")
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let mut sessions = sessions["data"].as_array().unwrap().clone();
+    sessions.sort_by_key(|session| !session["current"].as_bool().unwrap());
+    sessions
+        .iter()
+        .map(|session| session["id"].as_i64().unwrap())
+        .collect()
+}
+
+async fn delete_session(
+    app: &TestApp, user: &TestUser, session_id: i64,
+) -> StatusCode {
+    app.delete(&format!("/users/me/sessions/{session_id}"))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn sessions_can_only_be_revoked_by_their_user() {
+    let app = spawn_app().await;
+    let owner = app.sign_up("owner").await;
+    let other = app.sign_up("other").await;
+    let admin = app.admin().await;
+    app.log_in(&owner).await;
+
+    let [current, second] = session_ids(&app, &owner).await[..] else {
+        panic!("expected the signup and the login session");
+    };
+
+    // someone else's sessions look like they do not exist, even to admins
+    assert_eq!(
+        delete_session(&app, &other, second).await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        delete_session(&app, &admin, second).await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        delete_session(&app, &owner, second).await,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        delete_session(&app, &owner, current).await,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        delete_session(&app, &admin, i32::MAX as i64).await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn access_tokens_are_only_visible_to_their_user() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let admin = app.admin().await;
+    app.create_access_token(&owner).await;
+
+    let tokens: Value = app
+        .get("/users/me/tokens")
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token_id = tokens["data"][0]["id"].as_i64().unwrap();
+
+    for request in [
+        app.get(&format!("/users/me/tokens/{token_id}")),
+        app.delete(&format!("/users/me/tokens/{token_id}")),
+    ] {
+        let response = request.bearer_auth(&admin.token).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    let response = app
+        .get(&format!("/users/me/tokens/{token_id}"))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::{spawn_app, TestApp, TestUser};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+async fn add_item(app: &TestApp, user: &TestUser, task_id: i32) {
+    let response = app
+        .post(&format!("/tasks/{task_id}/items"))
+        .bearer_auth(&user.token)
+        .json(&json!({ "title": "step" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+async fn patch_deleted_at(
+    app: &TestApp, user: &TestUser, task_id: i32, deleted_at: Value,
+) {
+    let response = app
+        .patch(&format!("/tasks/{task_id}"))
+        .bearer_auth(&user.token)
+        .json(&json!({ "deleted_at": deleted_at }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// How many live items the task shows, from the trash when it is deleted.
+async fn item_count(
+    app: &TestApp, user: &TestUser, task_id: i32, path: &str,
+) -> i64 {
+    let body: Value = app
+        .get(path)
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let task = match body["data"].as_array() {
+        Some(tasks) => tasks
+            .iter()
+            .find(|task| task["id"] == task_id)
+            .expect("the task is not listed")
+            .clone(),
+        None => body,
+    };
+
+    task["items"]["total"].as_i64().unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn patching_deleted_at_trashes_and_restores_items() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let admin = app.admin().await;
+    let task_id = app.create_task(&owner, "with items").await;
+    add_item(&app, &owner, task_id).await;
+    add_item(&app, &owner, task_id).await;
+
+    patch_deleted_at(&app, &owner, task_id, json!("2024-01-01T00:00:00Z"))
+        .await;
+    assert_eq!(item_count(&app, &owner, task_id, "/tasks/trash").await, 0);
+
+    patch_deleted_at(&app, &admin, task_id, Value::Null).await;
+    assert_eq!(
+        item_count(&app, &owner, task_id, &format!("/tasks/{task_id}")).await,
+        2
+    );
+}
+
+async fn trash(app: &TestApp, user: &TestUser, task_id: i32) {
+    let response = app
+        .delete(&format!("/tasks/{task_id}"))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn trashed_tasks_are_not_found() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let admin = app.admin().await;
+    let task_id = app.create_task(&owner, "trashed").await;
+    trash(&app, &owner, task_id).await;
+
+    for request in [
+        app.get(&format!("/tasks/{task_id}")),
+        app.patch(&format!("/tasks/{task_id}"))
+            .json(&json!({ "title": "patched" })),
+        app.put(&format!("/tasks/{task_id}"))
+            .json(&json!({ "title": "replaced" })),
+        app.delete(&format!("/tasks/{task_id}")),
+        app.post(&format!("/tasks/{task_id}/complete")),
+        app.get(&format!("/tasks/{task_id}/items")),
+    ] {
+        let response = request.bearer_auth(&owner.token).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    let response = app
+        .post(&format!("/tasks/{task_id}/restore"))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        item_count(&app, &owner, task_id, &format!("/tasks/{task_id}")).await,
+        0
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn only_project_owners_delete_tasks_permanently() {
+    let app = spawn_app().await;
+    let owner = app.verified_user("owner").await;
+    let editor = app.verified_user("editor").await;
+    let project_id = app.shared_project(&owner, &editor, "editor").await;
+
+    let task: Value = app
+        .post("/tasks")
+        .bearer_auth(&owner.token)
+        .json(&json!({ "title": "shared", "project_id": project_id }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let task_id = task["id"].as_i64().unwrap() as i32;
+    trash(&app, &editor, task_id).await;
+
+    let path = format!("/tasks/{task_id}?permanent=true");
+    let response = app.delete(&path).bearer_auth(&editor.token).send().await;
+    assert_eq!(response.unwrap().status(), StatusCode::FORBIDDEN);
+
+    // trashed tasks can still be purged
+    let response = app.delete(&path).bearer_auth(&owner.token).send().await;
+    assert_eq!(response.unwrap().status(), StatusCode::OK);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{spawn_app, TestApp, TestUser};
+use reqwest::StatusCode;
+use serde_json::json;
+
+/// The token from the last verification email sent to the user.
+fn verification_token(app: &TestApp, user: &TestUser) -> String {
+    let email = app
+        .mailer
+        .sent()
+        .into_iter()
+        .rev()
+        .find(|email| {
+            email.to == user.username
+                && email.subject == "Confirm your email address"
+        })
+        .expect("no verification email was sent");
+
+    email
+        .body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.lines().next())
+        .expect("the email has no verification link")
+        .to_owned()
+}
+
+async fn verify(app: &TestApp, token: &str) -> StatusCode {
+    app.post("/users/verify")
+        .json(&json!({ "token": token }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn list_tasks(app: &TestApp, user: &TestUser) -> StatusCode {
+    app.get("/tasks")
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn signing_up_sends_a_verification_email() {
+    let app = spawn_app().await;
+    let user = app.sign_up("new").await;
+
+    assert_eq!(list_tasks(&app, &user).await, StatusCode::FORBIDDEN);
+
+    let token = verification_token(&app, &user);
+    assert_eq!(verify(&app, &token).await, StatusCode::OK);
+    assert_eq!(list_tasks(&app, &user).await, StatusCode::OK);
+
+    // tokens work once
+    assert_eq!(verify(&app, &token).await, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn resending_replaces_the_verification_email() {
+    let app = spawn_app().await;
+    let user = app.sign_up("resend").await;
+    let first = verification_token(&app, &user);
+
+    let response = app
+        .post("/users/verify/resend")
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let second = verification_token(&app, &user);
+    assert_ne!(first, second);
+    assert_eq!(verify(&app, &second).await, StatusCode::OK);
+
+    let response = app
+        .post("/users/verify/resend")
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore = "needs a database in TEST_DATABASE_URL"]
+async fn made_up_tokens_are_rejected() {
+    let app = spawn_app().await;
+
+    assert_eq!(verify(&app, "not-a-token").await, StatusCode::BAD_REQUEST);
+}